@@ -40,15 +40,28 @@ impl Registry {
             apis: vec![
                 API {
                     name: "list_collection".to_string(),
-                    description: "列出所有的集合的名称".to_string(),
+                    description: "列出所有的集合的名称、描述、文档数量与主题示例".to_string(),
                     parameters: Some(vec![]),
                 },
                 API {
                     name: "query".to_string(),
-                    description: "从指定名称的集合中查询相关信息。collection_name表示名称，text表示要查询的内容".to_string(),
+                    description: "从指定名称的集合中查询相关信息。collection_name表示名称，text表示要查询的内容，\
+                        where为可选的元数据过滤条件(JSON字符串)，可按source、title、author、timestamp、page(PDF页码)及入库时附加的字段过滤，\
+                        如 {\"department\": \"finance\", \"year\": {\"$gte\": 2023}}，支持$eq $ne $gt $gte $lt $lte $in $nin $and $or".to_string(),
                     parameters: Some(vec![
                         "collection_name".to_string(), 
                         "text".to_string(),
+                        "where".to_string(),
+                    ]),
+                },
+                API {
+                    name: "search".to_string(),
+                    description: "同时在多个集合中查询相关信息，不确定信息位于哪个集合时使用。collection_names为逗号分隔的集合名称，\
+                        为空时查询所有集合，text与where的含义与query相同，每条结果都会标注所属的集合".to_string(),
+                    parameters: Some(vec![
+                        "collection_names".to_string(),
+                        "text".to_string(),
+                        "where".to_string(),
                     ]),
                 },
             ]
@@ -70,6 +83,7 @@ impl Registry {
         match func_name.as_str() {
             "list_collection" => store.list_collections_llm().await,
             "query" => store.query_text_llm(params).await,
+            "search" => store.query_collections_llm(params).await,
             other => Err(anyhow::anyhow!("No such API: {}", other)),
         }
     }
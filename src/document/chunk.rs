@@ -1,12 +1,15 @@
 #[derive(Debug, Clone)]
 pub struct Chunk {
     pub content: String,
+    /// 切块在文档中的起始位置(字符数)
+    pub start: usize,
 }
 
 impl Chunk {
-    pub fn new(content: String) -> Self {
+    pub fn new(content: String, start: usize) -> Self {
         Self {
             content,
+            start,
         }
     }
 }
@@ -15,6 +18,7 @@ pub fn chunk_document(content: String, chunk_size: usize) -> Vec<Chunk> {
     let mut chunks = Vec::new();
     let mut char_indices = content.char_indices().peekable();
     let mut start = 0;
+    let mut start_char = 0;
     
     while char_indices.peek().is_some() {
         let mut end = content.len();
@@ -27,8 +31,10 @@ pub fn chunk_document(content: String, chunk_size: usize) -> Vec<Chunk> {
         }
         
         let chunk_content = content[start..end].to_string();
-        chunks.push(Chunk::new(chunk_content));
+        let chars = chunk_content.chars().count();
+        chunks.push(Chunk::new(chunk_content, start_char));
         start = end;
+        start_char += chars;
     }
     
     chunks
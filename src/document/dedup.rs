@@ -0,0 +1,96 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+const NUM_PERM: usize = 128;
+const BANDS: usize = 32;
+const ROWS: usize = NUM_PERM / BANDS;
+const SHINGLE: usize = 5;
+
+type Signature = [u64; NUM_PERM];
+
+/// 基于MinHash + LSH的近似重复文本索引
+pub struct MinHashIndex {
+    threshold: f32,
+    signatures: Vec<(String, Signature)>,
+    buckets: HashMap<(usize, u64), Vec<usize>>,
+}
+
+fn mix(mut x: u64) -> u64 {
+    // splitmix64
+    x = x.wrapping_add(0x9E3779B97F4A7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+    x ^ (x >> 31)
+}
+
+fn signature(text: &str) -> Signature {
+    // 忽略空白后按字符切分shingle, 对中文同样有效
+    let chars = text.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+    let mut sig = [u64::MAX; NUM_PERM];
+    for shingle in chars.windows(SHINGLE.min(chars.len().max(1))) {
+        let mut hasher = DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (i, slot) in sig.iter_mut().enumerate() {
+            *slot = (*slot).min(mix(hash ^ mix(i as u64)));
+        }
+    }
+    sig
+}
+
+fn similarity(a: &Signature, b: &Signature) -> f32 {
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f32 / NUM_PERM as f32
+}
+
+fn band_keys(sig: &Signature) -> impl Iterator<Item = (usize, u64)> + '_ {
+    sig.chunks(ROWS).enumerate().map(|(band, rows)| {
+        let mut hasher = DefaultHasher::new();
+        rows.hash(&mut hasher);
+        (band, hasher.finish())
+    })
+}
+
+impl MinHashIndex {
+    /// `threshold` 为估计的Jaccard相似度, 达到该值视为重复
+    pub fn new(threshold: f32) -> Self {
+        Self { threshold, signatures: Vec::new(), buckets: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, id: &str, text: &str) {
+        let sig = signature(text);
+        let index = self.signatures.len();
+        for key in band_keys(&sig) {
+            self.buckets.entry(key).or_default().push(index);
+        }
+        self.signatures.push((id.to_string(), sig));
+    }
+
+    /// 是否与索引中其他ID的文本重复, 同一ID的旧版本不计入
+    pub fn is_duplicate(&self, id: &str, text: &str) -> bool {
+        let sig = signature(text);
+        let duplicate = band_keys(&sig)
+            .filter_map(|key| self.buckets.get(&key))
+            .flatten()
+            .map(|&i| &self.signatures[i])
+            .any(|(other_id, other)| other_id != id && similarity(&sig, other) >= self.threshold);
+        duplicate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minhash() {
+        let disclaimer = "本报告仅供参考，不构成任何投资建议。投资者应独立判断并自行承担风险，本公司不对因使用本报告产生的任何损失负责。";
+        let mut index = MinHashIndex::new(0.8);
+        index.insert("a-0", disclaimer);
+
+        assert!(index.is_duplicate("b-0", disclaimer));
+        assert!(index.is_duplicate("b-1", &format!("{}（2024年版）", disclaimer)));
+        assert!(!index.is_duplicate("a-0", disclaimer));
+        assert!(!index.is_duplicate("b-2", "2023年公司营收同比增长15%，毛利率提升至32%，产能利用率维持高位。"));
+    }
+}
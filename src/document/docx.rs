@@ -1,9 +1,11 @@
+use std::collections::HashMap;
+use std::io::Cursor;
 use std::path::Path;
 use std::{fs::File, io::Read};
 
 use docx_rs::read_docx;
 
-pub fn extract(path: &Path) -> anyhow::Result<String> {
+pub fn extract(path: &Path) -> anyhow::Result<(String, HashMap<String, String>)> {
     let mut file = File::open(path)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
@@ -26,5 +28,71 @@ pub fn extract(path: &Path) -> anyhow::Result<String> {
         }
     }
 
-    Ok(text)
+    Ok((text, properties(&buffer)))
+}
+
+// docx_rs 不解析 docProps/core.xml, 直接从压缩包中读取
+fn properties(buffer: &[u8]) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    let mut core = String::new();
+    let read = zip::ZipArchive::new(Cursor::new(buffer))
+        .ok()
+        .and_then(|mut archive| {
+            archive.by_name("docProps/core.xml").ok()?.read_to_string(&mut core).ok()
+        });
+    if read.is_none() {
+        return properties;
+    }
+
+    let fields = [
+        ("dc:title", "title"),
+        ("dc:creator", "author"),
+        ("dcterms:created", "created"),
+        ("dcterms:modified", "modified"),
+    ];
+    for (tag, key) in fields {
+        let value = match xml_text(&core, tag) {
+            Some(value) => value,
+            None => continue,
+        };
+        let value = match key {
+            "created" | "modified" => match chrono::DateTime::parse_from_rfc3339(&value) {
+                Ok(date) => date.to_rfc3339(),
+                Err(_) => continue,
+            },
+            _ => value,
+        };
+        properties.insert(key.to_string(), value);
+    }
+    properties
+}
+
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}", tag))?;
+    let start = start + xml[start..].find('>')? + 1;
+    if xml[..start].ends_with("/>") {
+        return None;
+    }
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    let text = xml[start..end].trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_text() {
+        let core = r#"<cp:coreProperties><dc:title>年报 &amp; 季报</dc:title><dc:creator/>
+            <dcterms:created xsi:type="dcterms:W3CDTF">2023-05-01T08:00:00Z</dcterms:created></cp:coreProperties>"#;
+        assert_eq!(xml_text(core, "dc:title").as_deref(), Some("年报 & 季报"));
+        assert_eq!(xml_text(core, "dc:creator"), None);
+        assert_eq!(xml_text(core, "dcterms:created").as_deref(), Some("2023-05-01T08:00:00Z"));
+    }
 }
@@ -1,16 +1,22 @@
 pub mod pdf;
 pub mod docx;
 pub mod chunk;
+pub mod dedup;
+pub mod tokenizer;
 
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DocumentMetadata {
     pub path: PathBuf,
     pub metadata: HashMap<String, String>,
+    /// 每一页在文本中的起始位置(字符数), 只有PDF记录页码
+    #[serde(skip)]
+    pub page_starts: Vec<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,16 +24,67 @@ struct DocumentList {
     documents: Vec<DocumentMetadata>,
 }
 
-pub fn process_document(path: &Path) -> Result<String> {
-    let content = match path.extension().and_then(|s| s.to_str()) {
+impl DocumentMetadata {
+    /// 转换为切块的元数据: 来源路径、文档属性, 以及用于按时间排序的 `timestamp`
+    pub fn to_chunk_metadata(&self) -> Map<String, Value> {
+        let mut chunk_metadata = Map::new();
+        chunk_metadata.insert("source".to_string(), Value::from(self.path.display().to_string()));
+        for (key, value) in &self.metadata {
+            chunk_metadata.insert(key.clone(), Value::from(value.as_str()));
+        }
+
+        let date = self.metadata.get("created").or(self.metadata.get("modified"));
+        if let Some(date) = date.and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok()) {
+            chunk_metadata.insert("timestamp".to_string(), Value::from(date.timestamp()));
+        }
+        chunk_metadata
+    }
+
+    /// 文本中第 `offset` 个字符所在的页码, 从1开始
+    pub fn page_at(&self, offset: usize) -> Option<usize> {
+        match self.page_starts.partition_point(|&start| start <= offset) {
+            0 => None,
+            page => Some(page),
+        }
+    }
+}
+
+pub fn process_document(path: &Path) -> Result<(String, DocumentMetadata)> {
+    let (content, page_starts, metadata) = match path.extension().and_then(|s| s.to_str()) {
         Some("pdf") => pdf::extract(path)?,
-        Some("docx") => docx::extract(path)?,
+        Some("docx") => {
+            let (content, metadata) = docx::extract(path)?;
+            (content, Vec::new(), metadata)
+        },
         _ => anyhow::bail!("目前仅支持PDF和DOCX文件"),
     };
-    
+
     let processed = content.replace(|c: char| c.is_control(), "")
         .replace("。", ".")
         .replace("，", ",");
-        
-    Ok(processed)
+
+    // 以下替换不改变字符数, 页码的起始位置仍然有效
+    let metadata = DocumentMetadata { path: path.to_path_buf(), metadata, page_starts };
+    Ok((processed, metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chunk::chunk_document;
+
+    #[test]
+    fn test_page_at() {
+        let metadata = DocumentMetadata {
+            path: PathBuf::from("a.pdf"),
+            metadata: HashMap::new(),
+            page_starts: vec![0, 6, 12],
+        };
+        let chunks = chunk_document("第一页内容.第二页的内容.第三页".to_string(), 4);
+        let pages = chunks.iter().map(|chunk| metadata.page_at(chunk.start)).collect::<Vec<Option<usize>>>();
+        assert_eq!(pages, vec![Some(1), Some(1), Some(2), Some(3)]);
+
+        let docx = DocumentMetadata { page_starts: Vec::new(), ..metadata };
+        assert_eq!(docx.page_at(3), None);
+    }
 }
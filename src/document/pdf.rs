@@ -1,8 +1,104 @@
 use anyhow::Result;
-use pdf_extract::extract_text;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use pdf_extract::{decode_text_string, output_doc_page, Document, PlainTextOutput};
+use std::collections::HashMap;
 use std::path::Path;
 
-pub fn extract(path: &Path) -> Result<String> {
-    let content = extract_text(path)?;
-    Ok(content.replace(|c: char| c.is_control(), ""))
+/// 逐页提取文本, 同时返回每一页在文本中的起始位置(字符数)
+pub fn extract(path: &Path) -> Result<(String, Vec<usize>, HashMap<String, String>)> {
+    let mut doc = Document::load(path)?;
+    if doc.is_encrypted() {
+        doc.decrypt("")?;
+    }
+
+    let mut content = String::new();
+    let mut page_starts = Vec::new();
+    let mut chars = 0;
+    for page_num in doc.get_pages().into_keys() {
+        let mut page = String::new();
+        output_doc_page(&doc, &mut PlainTextOutput::new(&mut page), page_num)?;
+        let page = page.replace(|c: char| c.is_control(), "");
+        page_starts.push(chars);
+        chars += page.chars().count();
+        content.push_str(&page);
+    }
+    let properties = properties(&doc);
+
+    Ok((content, page_starts, properties))
+}
+
+// 读取Info字典中的标题、作者与日期
+fn properties(doc: &Document) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    let info = match doc.trailer.get(b"Info").and_then(|obj| doc.dereference(obj)) {
+        Ok((_, info)) => info,
+        Err(_) => return properties,
+    };
+    let info = match info.as_dict() {
+        Ok(info) => info,
+        Err(_) => return properties,
+    };
+
+    let fields: [(&[u8], &str); 4] = [
+        (b"Title", "title"),
+        (b"Author", "author"),
+        (b"CreationDate", "created"),
+        (b"ModDate", "modified"),
+    ];
+    for (field, key) in fields {
+        let value = match info.get(field).and_then(decode_text_string) {
+            Ok(value) => value.trim().to_string(),
+            Err(_) => continue,
+        };
+        let value = match key {
+            "created" | "modified" => match parse_date(&value) {
+                Some(date) => date.to_rfc3339(),
+                None => continue,
+            },
+            _ => value,
+        };
+        if !value.is_empty() {
+            properties.insert(key.to_string(), value);
+        }
+    }
+    properties
+}
+
+// PDF日期格式: D:YYYYMMDDHHmmSSOHH'mm', 除年份外均可省略
+fn parse_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.strip_prefix("D:").unwrap_or(value);
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.len() < 4 {
+        return None;
+    }
+    let zone = &value[digits.len()..];
+    let digits = &digits[..digits.len().min(14)];
+    let padded = format!("{}{}", digits, &"0101000000"[digits.len() - 4..]);
+    let naive = NaiveDateTime::parse_from_str(&padded, "%Y%m%d%H%M%S").ok()?;
+
+    let offset = match zone.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let zone: String = zone[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+            let hours: i32 = zone.get(0..2)?.parse().ok()?;
+            let minutes: i32 = zone.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+            let seconds = hours * 3600 + minutes * 60;
+            FixedOffset::east_opt(if sign == '-' { -seconds } else { seconds })?
+        },
+        _ => FixedOffset::east_opt(0)?,
+    };
+    offset.from_local_datetime(&naive).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date() {
+        let date = parse_date("D:20230815093000+08'00'").unwrap();
+        assert_eq!(date.to_rfc3339(), "2023-08-15T09:30:00+08:00");
+        let date = parse_date("D:2021").unwrap();
+        assert_eq!(date.to_rfc3339(), "2021-01-01T00:00:00+00:00");
+        assert!(parse_date("unknown").is_none());
+    }
 }
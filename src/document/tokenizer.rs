@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+use std::fs;
+use anyhow::Context;
+use jieba_rs::Jieba;
+use crate::Config;
+
+/// 基于jieba的中文分词, 用于关键词检索
+pub struct Tokenizer {
+    jieba: Jieba,
+    stop_words: HashSet<String>,
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Self { jieba: Jieba::new(), stop_words: HashSet::new() }
+    }
+
+    /// 加载配置中的用户词典(`user_dicts`)与停用词表(`stop_words`)
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let mut tokenizer = Self::new();
+        for path in &config.user_dicts {
+            let dict = fs::read_to_string(path).with_context(|| format!("无法读取词典 {}", path))?;
+            tokenizer.add_dict(&dict);
+        }
+        for path in &config.stop_words {
+            let words = fs::read_to_string(path).with_context(|| format!("无法读取停用词表 {}", path))?;
+            tokenizer.stop_words.extend(
+                words.lines().map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty())
+            );
+        }
+        Ok(tokenizer)
+    }
+
+    /// jieba词典格式, 每行 `词 [词频] [词性]`, 省略词频时自动计算保证不被切开
+    pub fn add_dict(&mut self, dict: &str) {
+        for line in dict.lines() {
+            let mut fields = line.split_whitespace();
+            let word = match fields.next() {
+                Some(word) if !word.starts_with('#') => word,
+                _ => continue,
+            };
+            let (freq, tag) = match (fields.next(), fields.next()) {
+                (Some(field), tag) => match field.parse() {
+                    Ok(freq) => (Some(freq), tag),
+                    Err(_) => (None, Some(field)),
+                },
+                (None, _) => (None, None),
+            };
+            self.jieba.add_word(word, freq, tag);
+        }
+    }
+
+    /// 使该词不被切开所需的词频
+    pub fn suggest_freq(&self, word: &str) -> usize {
+        self.jieba.suggest_freq(word)
+    }
+
+    /// 搜索引擎模式分词, 英文统一小写, 去掉标点、空白与停用词
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.jieba.cut_for_search(text, true)
+            .into_iter()
+            .filter(|word| word.chars().any(|c| c.is_alphanumeric()))
+            .map(|word| word.to_lowercase())
+            .filter(|word| !self.stop_words.contains(word))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize("DDR5内存需求上升，FCBGA封装供不应求。");
+        assert!(tokens.contains(&"ddr5".to_string()));
+        assert!(tokens.contains(&"fcbga".to_string()));
+        assert!(!tokens.iter().any(|t| t == "，" || t == "。"));
+    }
+
+    #[test]
+    fn test_user_dict() {
+        let mut tokenizer = Tokenizer::new();
+        tokenizer.add_dict("# 行业术语\n归母净利润\n产能利用率 n\n");
+        tokenizer.stop_words.insert("的".to_string());
+        let tokens = tokenizer.tokenize("公司的归母净利润与产能利用率");
+        assert!(tokens.contains(&"归母净利润".to_string()));
+        assert!(tokens.contains(&"产能利用率".to_string()));
+        assert!(!tokens.contains(&"的".to_string()));
+    }
+}
@@ -0,0 +1,239 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+use super::{Embedder, EmbeddingProvider};
+
+pub const DEFAULT_DIR: &str = ".docster/embedding-cache";
+pub const DEFAULT_SIZE_MB: u64 = 512;
+
+/// 磁盘上的向量缓存, 目录结构为 `<dir>/<模型>/<哈希前两位>/<sha256(范围, 文本)>`
+///
+/// 范围由服务提供方与维度组成, 同名模型在不同服务或维度下的向量互不混用;
+/// 每个文件保存小端序的f32向量, 超出容量时按最近使用时间淘汰
+pub struct EmbeddingCache {
+    dir: PathBuf,
+    limit: u64,
+    // 首次写入时才统计目录大小
+    size: Mutex<Option<u64>>,
+}
+
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+    pub models: Vec<(String, usize, u64)>,
+}
+
+// 模型名称作为目录名, 替换掉路径分隔符等字符
+fn model_dir(model: &str) -> String {
+    model.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+        .collect()
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    Some(bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+}
+
+fn cache_files(dir: &Path) -> impl Iterator<Item = walkdir::DirEntry> {
+    WalkDir::new(dir).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file())
+}
+
+impl EmbeddingCache {
+    pub fn new(dir: &Path, limit: u64) -> Self {
+        Self { dir: dir.to_path_buf(), limit, size: Mutex::new(None) }
+    }
+
+    fn entry_path(&self, model: &str, scope: &str, text: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(scope.as_bytes());
+        hasher.update([0]);
+        hasher.update(text.as_bytes());
+        let hash = format!("{:x}", hasher.finalize());
+        self.dir.join(model_dir(model)).join(&hash[..2]).join(hash)
+    }
+
+    pub fn get(&self, model: &str, scope: &str, text: &str) -> Option<Vec<f32>> {
+        let path = self.entry_path(model, scope, text);
+        let embedding = decode(&fs::read(&path).ok()?)?;
+        // 更新修改时间作为最近使用时间, 失败不影响读取
+        if let Ok(file) = File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(embedding)
+    }
+
+    pub fn put(&self, model: &str, scope: &str, text: &str, embedding: &[f32]) -> anyhow::Result<()> {
+        let path = self.entry_path(model, scope, text);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // 先写临时文件再重命名, 避免并发时读到写了一半的向量
+        let bytes = encode(embedding);
+        let tmp = path.with_extension("tmp");
+        File::create(&tmp)?.write_all(&bytes)?;
+        fs::rename(&tmp, &path)?;
+
+        let mut size = self.size.lock().unwrap();
+        let current = match *size {
+            Some(current) => current,
+            None => self.stats().bytes.saturating_sub(bytes.len() as u64),
+        };
+        let current = current + bytes.len() as u64;
+        *size = Some(if current > self.limit { self.evict()? } else { current });
+        Ok(())
+    }
+
+    // 按最近使用时间删除, 直到降到容量的90%以下, 返回剩余大小
+    fn evict(&self) -> anyhow::Result<u64> {
+        let mut files = cache_files(&self.dir)
+            .filter_map(|e| {
+                let metadata = e.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), e.into_path()))
+            })
+            .collect::<Vec<_>>();
+        files.sort_by_key(|(modified, _, _)| *modified);
+
+        let mut total = files.iter().map(|(_, len, _)| len).sum::<u64>();
+        let target = self.limit / 10 * 9;
+        for (_, len, path) in files {
+            if total <= target { break }
+            fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(total)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = CacheStats { entries: 0, bytes: 0, models: Vec::new() };
+        let models = fs::read_dir(&self.dir).into_iter().flatten().flatten()
+            .filter(|e| e.path().is_dir());
+        for model in models {
+            let (entries, bytes) = cache_files(&model.path())
+                .filter_map(|e| e.metadata().ok())
+                .fold((0, 0), |(n, size), m| (n + 1, size + m.len()));
+            stats.entries += entries;
+            stats.bytes += bytes;
+            stats.models.push((model.file_name().to_string_lossy().to_string(), entries, bytes));
+        }
+        stats.models.sort();
+        stats
+    }
+
+    /// 清空缓存, 指定模型时只删除该模型的条目
+    pub fn clear(&self, model: Option<&str>) -> anyhow::Result<()> {
+        let dir = match model {
+            Some(model) => self.dir.join(model_dir(model)),
+            None => self.dir.clone(),
+        };
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        *self.size.lock().unwrap() = None;
+        Ok(())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+/// 在向量服务前加一层磁盘缓存, 全部命中时不会请求服务
+pub struct CachedEmbedder {
+    inner: Box<dyn Embedder>,
+    cache: EmbeddingCache,
+    // 服务提供方与维度, 参与缓存键的计算
+    scope: String,
+}
+
+impl CachedEmbedder {
+    pub fn new(inner: Box<dyn Embedder>, provider: EmbeddingProvider, cache: EmbeddingCache) -> Self {
+        let scope = format!("{:?}/{}", provider, inner.dimension());
+        Self { inner, cache, scope }
+    }
+}
+
+#[async_trait]
+impl Embedder for CachedEmbedder {
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let model = self.inner.model();
+        let mut embeddings = texts.iter()
+            .map(|text| self.cache.get(model, &self.scope, text))
+            .collect::<Vec<Option<Vec<f32>>>>();
+
+        let missing = (0..texts.len()).filter(|&i| embeddings[i].is_none()).collect::<Vec<usize>>();
+        if !missing.is_empty() {
+            let missing_texts = missing.iter().map(|&i| texts[i]).collect::<Vec<&str>>();
+            let computed = self.inner.embed(&missing_texts).await?;
+            for (i, embedding) in missing.into_iter().zip(computed) {
+                if let Err(err) = self.cache.put(model, &self.scope, texts[i], &embedding) {
+                    eprintln!("写入向量缓存失败: {}", err);
+                }
+                embeddings[i] = Some(embedding);
+            }
+        }
+
+        embeddings.into_iter()
+            .map(|embedding| embedding.ok_or_else(|| anyhow::anyhow!("向量服务返回的数量与输入不一致")))
+            .collect()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn max_input(&self) -> usize {
+        self.inner.max_input()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("docster-cache-test-{}", std::process::id()));
+        let cache = EmbeddingCache::new(&dir, 64);
+        let embedding = vec![0.5f32, -1.0, 2.25];
+
+        assert!(cache.get("embedding-3", "Zhipu/3", "hello").is_none());
+        cache.put("embedding-3", "Zhipu/3", "hello", &embedding)?;
+        assert_eq!(cache.get("embedding-3", "Zhipu/3", "hello"), Some(embedding.clone()));
+        assert!(cache.get("other/model", "Zhipu/3", "hello").is_none());
+        // 同名模型换了服务或维度不能命中
+        assert!(cache.get("embedding-3", "Openai/3", "hello").is_none());
+        assert!(cache.get("embedding-3", "Zhipu/1024", "hello").is_none());
+
+        // 每条12字节, 超过64字节后淘汰到57字节以下
+        for i in 0..10 {
+            cache.put("embedding-3", "Zhipu/3", &i.to_string(), &embedding)?;
+            assert!(cache.stats().bytes <= 64);
+        }
+        let stats = cache.stats();
+        assert_eq!(stats.models.len(), 1);
+
+        cache.clear(None)?;
+        assert_eq!(cache.stats().entries, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use tokio::sync::Mutex;
+use tokio::time::{sleep_until, Instant};
+use anyhow::Context;
+
+const DEFAULT_TIMEOUT: u64 = 60;
+const DEFAULT_MAX_RETRIES: u32 = 5;
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// 向量服务请求的超时、重试与限流设置
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    pub timeout: Duration,
+    pub max_retries: u32,
+    /// 每分钟最多请求次数, 为空时不限流
+    pub rpm: Option<u32>,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+            max_retries: DEFAULT_MAX_RETRIES,
+            rpm: None,
+        }
+    }
+}
+
+impl HttpOptions {
+    pub fn new(timeout: Option<u64>, max_retries: Option<u32>, rpm: Option<u32>) -> Self {
+        Self {
+            timeout: Duration::from_secs(timeout.unwrap_or(DEFAULT_TIMEOUT)),
+            max_retries: max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            rpm: rpm.filter(|rpm| *rpm > 0),
+        }
+    }
+}
+
+/// 按固定间隔放行请求, 保证每分钟不超过 `rpm` 次
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(rpm: u32) -> Self {
+        Self { interval: Duration::from_secs(60) / rpm, next: Mutex::new(Instant::now()) }
+    }
+
+    async fn acquire(&self) {
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        if *next > now {
+            sleep_until(*next).await;
+        }
+        *next = (*next).max(now) + self.interval;
+    }
+}
+
+/// 带重试与限流的HTTP客户端, 429与5xx按指数退避重试
+pub struct HttpClient {
+    client: Client,
+    max_retries: u32,
+    limiter: Option<RateLimiter>,
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// 支持秒数与HTTP日期两种格式
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(seconds as u64))
+}
+
+// 指数退避, 加上最多一半的随机抖动, 避免多个进程同时重试
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(MAX_DELAY);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+    let jitter = delay.mul_f64((nanos % 1000) as f64 / 2000.0);
+    delay + jitter
+}
+
+// 服务通过Retry-After指定的等待时间不超过 MAX_DELAY, 更长时直接报错, 避免长时间卡住
+fn retry_delay(retry_after: Option<Duration>, attempt: u32) -> anyhow::Result<Duration> {
+    match retry_after {
+        Some(delay) if delay > MAX_DELAY => anyhow::bail!(
+            "向量服务要求 {} 秒后重试, 超过最长等待时间 {} 秒, 请稍后再试",
+            delay.as_secs(), MAX_DELAY.as_secs()
+        ),
+        Some(delay) => Ok(delay),
+        None => Ok(backoff(attempt)),
+    }
+}
+
+impl HttpClient {
+    pub fn new(options: &HttpOptions) -> Self {
+        let client = Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.timeout.min(Duration::from_secs(10)))
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            max_retries: options.max_retries,
+            limiter: options.rpm.map(RateLimiter::new),
+        }
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// 发送请求, `build` 在每次重试时重新构造请求; 非200时错误中带上响应内容
+    pub async fn send(&self, build: impl Fn() -> RequestBuilder) -> anyhow::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+
+            let delay = match build().send().await {
+                Ok(response) if response.status() == StatusCode::OK => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = response.headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(parse_retry_after);
+                    let body = response.text().await.unwrap_or_default();
+                    if !is_retryable(status) || attempt >= self.max_retries {
+                        anyhow::bail!("请求向量模型失败:\n\t错误码: {}\n\t内容: {}", status, body);
+                    }
+                    let delay = retry_delay(retry_after, attempt)
+                        .with_context(|| format!("请求向量模型失败:\n\t错误码: {}\n\t内容: {}", status, body))?;
+                    eprintln!("请求向量模型失败({}), 第{}次重试", status, attempt + 1);
+                    delay
+                },
+                Err(err) if (err.is_timeout() || err.is_connect()) && attempt < self.max_retries => {
+                    eprintln!("请求向量模型失败({}), 第{}次重试", err, attempt + 1);
+                    backoff(attempt)
+                },
+                Err(err) => return Err(err).context("Failed to send request to embedding model"),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+
+        assert!(backoff(0) >= BASE_DELAY && backoff(0) <= BASE_DELAY * 3 / 2);
+        assert!(backoff(20) <= MAX_DELAY * 3 / 2);
+
+        assert_eq!(retry_delay(Some(Duration::from_secs(3)), 0).ok(), Some(Duration::from_secs(3)));
+        assert!(retry_delay(Some(Duration::from_secs(86400)), 0).is_err());
+        assert!(retry_delay(None, 0).is_ok_and(|delay| delay >= BASE_DELAY));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(600);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
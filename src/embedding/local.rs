@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use super::Embedder;
+
+pub const DEFAULT_MODEL: &str = "hash-ngram";
+const MIN_N: usize = 1;
+const MAX_N: usize = 3;
+
+/// 进程内的字符n-gram哈希向量, 不依赖网络与模型文件, 适合离线环境
+///
+/// 效果不如语义模型, 但对中文关键词与近似文本的召回足够使用
+pub struct LocalEmbedder {
+    model: String,
+    dimension: usize,
+}
+
+// FNV-1a, 结果不随Rust版本变化, 保证写入的向量与之后的查询一致
+fn fnv1a(chars: &[char]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for c in chars {
+        for byte in (*c as u32).to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+impl LocalEmbedder {
+    pub fn new(model: &str, dimension: usize) -> Self {
+        let model = if model.is_empty() { DEFAULT_MODEL } else { model };
+        Self { model: model.to_string(), dimension }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let chars = text.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(|c| c.to_lowercase())
+            .collect::<Vec<char>>();
+        let mut vector = vec![0f32; self.dimension];
+        for n in MIN_N..=MAX_N {
+            for gram in chars.windows(n) {
+                let hash = fnv1a(gram);
+                let index = (hash % self.dimension as u64) as usize;
+                // 用最高位决定符号, 减少哈希冲突带来的偏差
+                let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+                vector[index] += sign;
+            }
+        }
+
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn max_input(&self) -> usize {
+        usize::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_local_embed() -> anyhow::Result<()> {
+        let embedder = LocalEmbedder::new("", 512);
+        let texts = ["公司营收同比增长15%", "公司营收同比增长20%", "董事会审议通过利润分配方案"];
+        let embeddings = embedder.embed(&texts).await?;
+
+        assert_eq!(embeddings[0].len(), 512);
+        assert!((cosine(&embeddings[0], &embeddings[0]) - 1.0).abs() < 1e-5);
+        assert!(cosine(&embeddings[0], &embeddings[1]) > cosine(&embeddings[0], &embeddings[2]));
+        assert_eq!(embeddings[0], embedder.embed(&texts[..1]).await?[0]);
+        Ok(())
+    }
+}
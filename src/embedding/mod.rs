@@ -1 +1,88 @@
-pub mod zhipu;
\ No newline at end of file
+pub mod zhipu;
+pub mod openai;
+pub mod ollama;
+pub mod local;
+pub mod cache;
+pub mod http;
+
+use std::path::Path;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::Config;
+
+/// 向量模型的服务提供方, 对应配置中的 `embedding_provider`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingProvider {
+    #[default]
+    Zhipu,
+    /// 兼容OpenAI `/v1/embeddings` 接口的服务, 如 vLLM、TEI 等自建服务
+    Openai,
+    /// Ollama 的 `/api/embed` 接口
+    Ollama,
+    /// 进程内计算, 无需网络
+    Local,
+}
+
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// 批量计算向量, 返回顺序与输入一致
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>>;
+
+    fn model(&self) -> &str;
+
+    /// 期望的向量维度
+    fn dimension(&self) -> usize;
+
+    /// 单次请求最多能提交的文本数
+    fn max_input(&self) -> usize;
+}
+
+/// 配置中当前服务提供方使用的模型名称
+pub fn configured_model(config: &Config) -> &str {
+    match config.embedding_provider {
+        EmbeddingProvider::Zhipu => &config.zhipu_embedding_model,
+        _ => &config.embedding_model,
+    }
+}
+
+pub fn from_config(config: &Config) -> Box<dyn Embedder> {
+    with_model(config, configured_model(config))
+}
+
+pub fn cache_from_config(config: &Config) -> cache::EmbeddingCache {
+    let dir = match config.embedding_cache_dir.as_str() {
+        "" => cache::DEFAULT_DIR,
+        dir => dir,
+    };
+    let size_mb = match config.embedding_cache_mb {
+        0 => cache::DEFAULT_SIZE_MB,
+        size_mb => size_mb,
+    };
+    cache::EmbeddingCache::new(Path::new(dir), size_mb * 1024 * 1024)
+}
+
+/// 使用配置中的服务提供方, 但替换为指定的模型
+pub fn with_model(config: &Config, model: &str) -> Box<dyn Embedder> {
+    let dim = config.embedding_dim as usize;
+    let http = http::HttpOptions::new(
+        config.embedding_timeout, config.embedding_max_retries, config.embedding_rpm
+    );
+    let embedder: Box<dyn Embedder> = match config.embedding_provider {
+        EmbeddingProvider::Zhipu => {
+            let options = zhipu::ZhipuOptions::new(
+                &config.zhipu_api_key, &config.zhipu_url, &model.to_string()
+            );
+            Box::new(zhipu::EmbeddingClient::new(options).with_dimension(dim).with_http(&http))
+        },
+        EmbeddingProvider::Openai => Box::new(openai::OpenAIEmbedder::new(
+            &config.embedding_url, &config.embedding_api_key, model, dim, &http
+        )),
+        EmbeddingProvider::Ollama => Box::new(ollama::OllamaEmbedder::new(
+            &config.embedding_url, model, dim, &http
+        )),
+        // 本地计算比读缓存还快, 不需要缓存
+        EmbeddingProvider::Local => return Box::new(local::LocalEmbedder::new(model, dim)),
+    };
+    Box::new(cache::CachedEmbedder::new(embedder, config.embedding_provider, cache_from_config(config)))
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use anyhow::Context;
+use super::http::{HttpClient, HttpOptions};
+use super::Embedder;
+
+// Ollama 没有明确的上限, 过大的批次会占满本地显存
+const MAX_INPUT: usize = 64;
+
+/// Ollama 的向量接口, `url` 形如 `http://localhost:11434/api/embed`
+pub struct OllamaEmbedder {
+    client: HttpClient,
+    url: String,
+    model: String,
+    dimension: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+impl OllamaEmbedder {
+    pub fn new(url: &str, model: &str, dimension: usize, http: &HttpOptions) -> Self {
+        Self {
+            client: HttpClient::new(http),
+            url: url.to_string(),
+            model: model.to_string(),
+            dimension,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for OllamaEmbedder {
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let request = OllamaRequest { model: &self.model, input: texts };
+        let response = self.client.send(|| self.client.post(&self.url).json(&request))
+            .await
+            .with_context(|| format!("Failed to send request to {}", self.url))?;
+
+        let embedding_response = response
+            .json::<OllamaResponse>()
+            .await
+            .context("Failed to parse Ollama response")?;
+
+        Ok(embedding_response.embeddings)
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn max_input(&self) -> usize {
+        MAX_INPUT
+    }
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use anyhow::Context;
+use super::http::{HttpClient, HttpOptions};
+use super::Embedder;
+
+// OpenAI 单次请求最多 2048 条输入
+const MAX_INPUT: usize = 2048;
+
+/// 兼容OpenAI接口的向量服务, `url` 为完整的 embeddings 地址
+pub struct OpenAIEmbedder {
+    client: HttpClient,
+    url: String,
+    api_key: String,
+    model: String,
+    dimension: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAIRequest<'a> {
+    input: &'a [&'a str],
+    model: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIResponse {
+    data: Vec<OpenAIEmbed>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbed {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAIEmbedder {
+    pub fn new(url: &str, api_key: &str, model: &str, dimension: usize, http: &HttpOptions) -> Self {
+        Self {
+            client: HttpClient::new(http),
+            url: url.to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            dimension,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAIEmbedder {
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let request = OpenAIRequest { input: texts, model: &self.model };
+        let response = self.client.send(|| {
+            let builder = self.client.post(&self.url).json(&request);
+            // 自建服务通常不需要密钥
+            if self.api_key.is_empty() {
+                builder
+            } else {
+                builder.header("Authorization", format!("Bearer {}", self.api_key))
+            }
+        }).await.with_context(|| format!("Failed to send request to {}", self.url))?;
+
+        let mut data = response
+            .json::<OpenAIResponse>()
+            .await
+            .context("Failed to parse embedding response")?
+            .data;
+        data.sort_by_key(|embed| embed.index);
+        Ok(data.into_iter().map(|embed| embed.embedding).collect())
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn max_input(&self) -> usize {
+        MAX_INPUT
+    }
+}
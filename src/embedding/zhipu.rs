@@ -1,6 +1,11 @@
-use reqwest::Client;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use anyhow::Context;
+use super::http::{HttpClient, HttpOptions};
+use super::Embedder;
+
+// 智谱 embedding-3 单次请求最多 64 条输入
+const MAX_INPUT: usize = 64;
 
 #[derive(Debug, Clone)]
 pub struct ZhipuOptions {
@@ -38,13 +43,24 @@ impl ZhipuOptions {
 }
 
 pub struct EmbeddingClient {
-    client: Client,
+    client: HttpClient,
     options: ZhipuOptions,
+    dimension: usize,
 }
 
 impl EmbeddingClient {
     pub fn new(options: ZhipuOptions) -> Self {
-        Self { client: Client::new(), options}
+        Self { client: HttpClient::new(&HttpOptions::default()), options, dimension: 2048 }
+    }
+
+    pub fn with_http(mut self, options: &HttpOptions) -> Self {
+        self.client = HttpClient::new(options);
+        self
+    }
+
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
     }
 
     pub async fn zhipu_embedding(&self, texts: &Vec<&str>) -> anyhow::Result<Vec<Embed>> {
@@ -53,18 +69,12 @@ impl EmbeddingClient {
             model: self.options.model.clone(),
         };
 
-        let response = self.client
+        let response = self.client.send(|| self.client
             .post(&self.options.url)
             .header("Authorization", format!("Bearer {}", self.options.api_key))
             .header("Content-Type", "application/json")
             .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to Zhipu API")?;
-
-        if response.status() != 200 {
-            return Err(anyhow::anyhow!("Request Failed to Embedding Model: {}", response.status()))
-        }
+        ).await.context("Failed to send request to Zhipu API")?;
 
         let embedding_response = response
             .json::<ZhipuResponse>()
@@ -75,6 +85,26 @@ impl EmbeddingClient {
     }
 }
 
+#[async_trait]
+impl Embedder for EmbeddingClient {
+    async fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let embeddings = self.zhipu_embedding(&texts.to_vec()).await?;
+        Ok(embeddings.into_iter().map(|embed| embed.embedding).collect())
+    }
+
+    fn model(&self) -> &str {
+        &self.options.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn max_input(&self) -> usize {
+        MAX_INPUT
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
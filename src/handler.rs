@@ -1,9 +1,18 @@
 use std::path::PathBuf;
 use clap::{command, Parser};
 use crate::Config;
+use document::DedupMode;
+use transfer::ConflictPolicy;
 
+mod backup;
+mod cache;
+mod dict;
 mod document;
+mod dry_run;
+mod inspect;
 mod query;
+mod similar;
+mod transfer;
 mod write;
 
 #[derive(Parser)]
@@ -25,6 +34,38 @@ pub enum Cli {
         #[arg(long, help = "输出文件路径")]
         path: PathBuf,
     },
+
+    /// 向量缓存管理命令
+    #[command(subcommand)]
+    Cache(CacheCommand),
+
+    /// 分词词典管理命令
+    #[command(subcommand)]
+    Dict(DictCommand),
+}
+
+#[derive(Parser)]
+pub enum DictCommand {
+    /// 从Markdown术语表生成jieba用户词典
+    Import {
+        #[arg(help = "术语表路径")]
+        path: PathBuf,
+
+        #[arg(short, long, help = "输出路径, 默认为术语表同目录下的 <文件名>.dict.txt")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Parser)]
+pub enum CacheCommand {
+    /// 查看缓存的条目数与占用空间
+    Stats,
+
+    /// 清空缓存
+    Clear {
+        #[arg(long, help = "只清除该模型的缓存")]
+        model: Option<String>,
+    },
 }
 
 #[derive(Parser)]
@@ -39,6 +80,36 @@ pub enum DocCommand {
 
         #[arg(short, long, help = "添加的集合名称")]
         name: String,
+
+        #[arg(long, help = "只处理匹配的文件, glob格式, 可重复")]
+        include: Vec<String>,
+
+        #[arg(long, help = "排除匹配的文件或目录, glob格式, 可重复. 各级目录下的.docsterignore同样生效, 其规则相对于所在目录")]
+        exclude: Vec<String>,
+
+        #[arg(long, value_parser = document::parse_size, help = "跳过超过该大小的文件, 如 20MB")]
+        max_file_size: Option<u64>,
+
+        #[arg(long, help = "仅列出将要处理的文件, 不上传")]
+        list_only: bool,
+
+        #[arg(long = "meta", value_parser = document::parse_meta, help = "附加到每个切块和集合上的元数据, 格式为 key=value, 可重复")]
+        meta: Vec<(String, serde_json::Value)>,
+
+        #[arg(long, help = "读取与文件同名的 <file>.meta.json 作为该文件的元数据")]
+        sidecar: bool,
+
+        #[arg(long, value_enum, help = "入库时丢弃与集合中已有内容近似重复的切块")]
+        dedup: Option<DedupMode>,
+
+        #[arg(long, requires = "dedup", help = "去重阈值, embedding默认0.95(余弦相似度), minhash默认0.8(Jaccard相似度)")]
+        dedup_threshold: Option<f32>,
+
+        #[arg(long, help = "只提取和切块, 输出统计报告, 不调用向量模型")]
+        dry_run: bool,
+
+        #[arg(long, requires = "dry_run", help = "将 dry-run 报告以JSON格式写入该文件")]
+        report: Option<PathBuf>,
     },
 
     List,
@@ -49,6 +120,120 @@ pub enum DocCommand {
     },
 
     Clean,
+
+    /// 查看指定ID的切块内容与元数据
+    Show {
+        #[arg(help = "集合名称")]
+        name: String,
+
+        #[arg(help = "切块ID")]
+        id: String,
+    },
+
+    /// 列出某个文件入库后的所有切块
+    Chunks {
+        #[arg(help = "集合名称")]
+        name: String,
+
+        #[arg(long, help = "入库时记录的文件路径")]
+        source: String,
+    },
+
+    /// 查找与给定文件或切块相似的文档
+    Similar {
+        #[arg(help = "集合名称")]
+        name: String,
+
+        #[arg(long, required_unless_present = "id", conflicts_with = "id", help = "要比较的文件, 切块后计算向量")]
+        file: Option<PathBuf>,
+
+        #[arg(long, help = "要比较的切块ID, 直接使用已保存的向量")]
+        id: Option<String>,
+
+        #[arg(short = 'n', long, default_value_t = 10, help = "列出的文档数")]
+        limit: usize,
+    },
+
+    /// 统计集合的文档数、切块数、平均长度、向量维度与来源
+    Stats {
+        #[arg(help = "集合名称")]
+        name: String,
+    },
+
+    /// 导出集合(含向量)到JSONL备份文件, 以.gz结尾时压缩
+    Export {
+        #[arg(help = "集合名称")]
+        name: String,
+
+        #[arg(help = "备份文件路径")]
+        path: PathBuf,
+    },
+
+    /// 从备份文件恢复集合, 无需重新计算向量
+    Import {
+        #[arg(help = "备份文件路径")]
+        path: PathBuf,
+
+        #[arg(short, long, help = "恢复到的集合名称, 默认使用备份中的名称")]
+        name: Option<String>,
+    },
+
+    /// 设置集合描述, 智能体据此选择查询的集合
+    Describe {
+        #[arg(help = "集合名称")]
+        name: String,
+
+        #[arg(help = "集合内容的描述")]
+        description: String,
+    },
+
+    /// 使用新的向量模型重新计算集合的向量, 完成后替换原集合
+    Reembed {
+        #[arg(help = "集合名称")]
+        name: String,
+
+        #[arg(long, help = "新的向量模型名称")]
+        model: String,
+
+        #[arg(long, help = "保留原集合, 重命名为 <集合名称>-old")]
+        keep_old: bool,
+    },
+
+    /// 重命名集合
+    Rename {
+        #[arg(help = "原集合名称")]
+        from: String,
+
+        #[arg(help = "新集合名称")]
+        to: String,
+    },
+
+    /// 复制集合的全部记录(含向量)到另一个集合
+    Copy {
+        #[arg(help = "来源集合名称")]
+        from: String,
+
+        #[arg(help = "目标集合名称")]
+        to: String,
+
+        #[arg(long, value_enum, default_value_t = ConflictPolicy::Skip, help = "ID冲突时的处理方式")]
+        on_conflict: ConflictPolicy,
+    },
+
+    /// 将多个集合合并到目标集合
+    Merge {
+        #[arg(required = true, help = "来源集合名称")]
+        sources: Vec<String>,
+
+        #[arg(long, help = "目标集合名称")]
+        into: String,
+
+        #[arg(long, value_enum, default_value_t = ConflictPolicy::Skip, help = "ID冲突时的处理方式")]
+        on_conflict: ConflictPolicy,
+
+        #[arg(long, help = "合并完成后删除来源集合")]
+        delete_sources: bool,
+    },
 }
 
 pub async fn handler(args: Cli, config: Config, pool: deadpool_postgres::Pool) -> anyhow::Result<()> {
@@ -56,19 +241,71 @@ pub async fn handler(args: Cli, config: Config, pool: deadpool_postgres::Pool) -
         Cli::Doc(cmd) => handle_doc_command(cmd, config).await,
         Cli::Chat { save } => query::handle_query_session(&pool, config, save).await,
         Cli::Write { path } => write::export_to_excel(&pool, path).await,
+        Cli::Cache(cmd) => {
+            let store = crate::embedding::cache_from_config(&config);
+            match cmd {
+                CacheCommand::Stats => cache::cache_stats(&store),
+                CacheCommand::Clear { model } => cache::clear_cache(&store, model.as_deref()),
+            }
+        }
+        Cli::Dict(DictCommand::Import { path, output }) => dict::import_glossary(&path, output.as_deref()),
     }
 }
 
 async fn handle_doc_command(cmd: DocCommand, config: Config) -> anyhow::Result<()> {
     use document::*;
-    let store = crate::vector_store::VectorStore::from_config(&config).await?;
+    // list-only 与 dry-run 不需要连接数据库
+    let connect = || crate::vector_store::VectorStore::from_config(&config);
 
     match cmd {
-        DocCommand::Add { path, name, recursive } => {
-            add_documents(&store, path, &name, recursive, config.chunk_size as usize).await
+        DocCommand::Add {
+            path, name, recursive, include, exclude, max_file_size, list_only, meta, sidecar,
+            dedup, dedup_threshold, dry_run, report,
+        } => {
+            let options = AddOptions {
+                recursive,
+                chunk_size: config.chunk_size as usize,
+                filter: EntryFilter::new(&include, &exclude, max_file_size)?,
+                metadata: meta.into_iter().collect(),
+                sidecar,
+                dedup,
+                dedup_threshold,
+            };
+            if list_only {
+                return list_documents(&path, &options);
+            }
+            if dry_run {
+                return dry_run::dry_run_documents(&path, &options, report.as_deref(), config.embedding_price);
+            }
+            add_documents(&connect().await?, path, &name, options).await
+        }
+        DocCommand::List => list_collections(&connect().await?).await,
+        DocCommand::Remove { name } => remove_collection(&connect().await?, &name).await,
+        DocCommand::Clean => clean_collections(&connect().await?).await,
+        DocCommand::Show { name, id } => inspect::show_chunk(&connect().await?, &name, &id).await,
+        DocCommand::Chunks { name, source } => inspect::list_chunks(&connect().await?, &name, &source).await,
+        DocCommand::Similar { name, file, id, limit } => {
+            similar::similar_documents(
+                &connect().await?, &name, file.as_deref(), id.as_deref(), config.chunk_size as usize, limit
+            ).await
+        }
+        DocCommand::Stats { name } => inspect::collection_stats(&connect().await?, &name).await,
+        DocCommand::Export { name, path } => backup::export_collection(&connect().await?, &name, &path).await,
+        DocCommand::Import { path, name } => {
+            backup::import_collection(&connect().await?, &path, name.as_deref()).await
+        }
+        DocCommand::Describe { name, description } => {
+            describe_collection(&connect().await?, &name, &description).await
+        }
+        DocCommand::Reembed { name, model, keep_old } => {
+            transfer::reembed_collection(&connect().await?, &config, &name, &model, keep_old).await
+        }
+        DocCommand::Rename { from, to } => transfer::rename_collection(&connect().await?, &from, &to).await,
+        DocCommand::Copy { from, to, on_conflict } => {
+            transfer::copy_collection(&connect().await?, &from, &to, on_conflict).await
+        }
+        DocCommand::Merge { sources, into, on_conflict, delete_sources } => {
+            transfer::merge_collections(&connect().await?, &sources, &into, on_conflict, delete_sources).await
         }
-        DocCommand::List => list_collections(&store).await,
-        DocCommand::Remove { name } => remove_collection(&store, &name).await,
-        DocCommand::Clean => clean_collections(&store).await,
     }
 }
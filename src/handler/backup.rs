@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use anyhow::Context;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::vector_store::{Record, VectorStore};
+
+const FORMAT: &str = "docster-collection";
+const VERSION: u32 = 1;
+const PAGE_SIZE: usize = 500;
+
+/// 备份文件的第一行, 之后每行一条 `Record`
+#[derive(Serialize, Deserialize)]
+struct BackupHeader {
+    format: String,
+    version: u32,
+    collection: String,
+    metadata: Option<Map<String, Value>>,
+    count: usize,
+}
+
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// 导出集合到JSONL文件, 路径以 `.gz` 结尾时使用gzip压缩
+pub async fn export_collection(store: &VectorStore, name: &str, path: &Path) -> anyhow::Result<()> {
+    let count = store.count(name).await?;
+    let header = BackupHeader {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        collection: name.to_string(),
+        metadata: store.collection_metadata(name).await?,
+        count,
+    };
+
+    let file = File::create(path)?;
+    let mut writer: Box<dyn Write> = if is_gzip(path) {
+        Box::new(GzEncoder::new(file, Compression::default()))
+    } else {
+        Box::new(BufWriter::new(file))
+    };
+    writeln!(writer, "{}", serde_json::to_string(&header)?)?;
+
+    let mut offset: usize = 0;
+    loop {
+        let records = store.get_records(name, offset, PAGE_SIZE).await?;
+        if records.is_empty() { break }
+        for record in &records {
+            writeln!(writer, "{}", serde_json::to_string(record)?)?;
+        }
+        offset += records.len();
+        println!("已导出 {}/{}", offset, count);
+    }
+    writer.flush()?;
+    drop(writer);
+
+    println!("集合 {} 已导出到 {}", name, path.display());
+    Ok(())
+}
+
+/// 从备份文件恢复集合, `name` 为空时使用备份中的集合名称;
+/// 集合已存在时保留其元数据, 向量模型或维度与备份不一致时拒绝导入
+pub async fn import_collection(store: &VectorStore, path: &Path, name: Option<&str>) -> anyhow::Result<()> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if is_gzip(path) {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut lines = BufReader::new(reader).lines();
+
+    let header = lines.next().ok_or_else(|| anyhow::anyhow!("备份文件为空"))??;
+    let header: BackupHeader = serde_json::from_str(&header)
+        .map_err(|err| anyhow::anyhow!("无法识别的备份文件头: {}", err))?;
+    if header.format != FORMAT {
+        anyhow::bail!("不是docster的备份文件: {}", header.format);
+    }
+    if header.version > VERSION {
+        anyhow::bail!("备份文件版本 {} 高于当前支持的版本 {}", header.version, VERSION);
+    }
+
+    let name = name.unwrap_or(&header.collection);
+    // 在写入任何记录之前检查, 避免导入到一半才失败
+    store.open_target(name, header.metadata.as_ref()).await
+        .with_context(|| format!("无法将备份导入集合 {}", name))?;
+    let mut imported = 0;
+    let mut page = Vec::with_capacity(PAGE_SIZE);
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue }
+        let record: Record = serde_json::from_str(&line)
+            .map_err(|err| anyhow::anyhow!("第{}行记录无法解析: {}", i + 2, err))?;
+        page.push(record);
+
+        if page.len() == PAGE_SIZE {
+            imported += page.len();
+            store.upsert_records(name, std::mem::take(&mut page), header.metadata.as_ref()).await?;
+            println!("已导入 {}/{}", imported, header.count);
+        }
+    }
+    if !page.is_empty() {
+        imported += page.len();
+        store.upsert_records(name, page, header.metadata.as_ref()).await?;
+    }
+
+    println!("已将 {} 条记录导入集合 {}", imported, name);
+    Ok(())
+}
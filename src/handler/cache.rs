@@ -0,0 +1,26 @@
+use crate::embedding::cache::EmbeddingCache;
+
+const MB: f64 = 1024.0 * 1024.0;
+
+pub fn cache_stats(cache: &EmbeddingCache) -> anyhow::Result<()> {
+    let stats = cache.stats();
+    println!("\n缓存目录: {}", cache.dir().display());
+    println!("条目数: {}", stats.entries);
+    println!("占用空间: {:.1}MB / {:.0}MB", stats.bytes as f64 / MB, cache.limit() as f64 / MB);
+    if !stats.models.is_empty() {
+        println!("\n模型：");
+        for (model, entries, bytes) in stats.models {
+            println!("\t{} ({}条, {:.1}MB)", model, entries, bytes as f64 / MB);
+        }
+    }
+    Ok(())
+}
+
+pub fn clear_cache(cache: &EmbeddingCache, model: Option<&str>) -> anyhow::Result<()> {
+    cache.clear(model)?;
+    match model {
+        Some(model) => println!("已清除模型 {} 的向量缓存", model),
+        None => println!("已清空向量缓存"),
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::document::tokenizer::Tokenizer;
+
+// 术语前后的修饰词, 如 "包括电路"、"英飞凌等"
+const PREFIXES: [&str; 1] = ["包括"];
+const SUFFIXES: [&str; 1] = ["等"];
+// 括号内的示例列表, 如 "先进封装(如FCBGA)", 只在括号后去掉 "如", 不影响 "如意" 这类术语
+const EXAMPLE_MARKERS: [&str; 2] = ["(如", "（如"];
+
+fn clean_term(term: &str) -> Option<String> {
+    // 术语表中偶尔混入空格, 如 "归母净 利润"
+    let mut term = term.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    for prefix in PREFIXES {
+        if let Some(rest) = term.strip_prefix(prefix) { term = rest.to_string() }
+    }
+    for suffix in SUFFIXES {
+        if let Some(rest) = term.strip_suffix(suffix) { term = rest.to_string() }
+    }
+    // 纯英文与数字jieba本身不会切开, 不需要加入词典
+    let has_cjk = !term.is_ascii();
+    (has_cjk && term.chars().count() >= 2).then_some(term)
+}
+
+/// 从Markdown术语表中提取词条, 格式为 `- **术语**：词1、词2(别名/别名)`
+pub fn parse_glossary(text: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    let mut push = |term: &str| {
+        if let Some(term) = clean_term(term) {
+            if !terms.contains(&term) { terms.push(term) }
+        }
+    };
+
+    for line in text.lines() {
+        let line = match line.trim().strip_prefix("- ") {
+            Some(line) => line,
+            None => continue,
+        };
+        let (name, items) = match line.split_once('：').or_else(|| line.split_once(':')) {
+            Some((name, items)) => (name, items),
+            None => (line, ""),
+        };
+        push(name.trim().trim_matches('*'));
+
+        let items = EXAMPLE_MARKERS.iter().fold(items.to_string(), |items, marker| items.replace(marker, "("));
+        let items = items.replace(['（', '(', '）', ')', '/', '，', ',', '；', ';'], "、");
+        for item in items.split('、') {
+            push(item);
+        }
+    }
+    terms
+}
+
+fn default_output(path: &Path) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("glossary");
+    path.with_file_name(format!("{}.dict.txt", stem))
+}
+
+/// 将术语表转换为jieba用户词典, 词频取保证不被切开的最小值
+pub fn import_glossary(path: &Path, output: Option<&Path>) -> anyhow::Result<()> {
+    let text = fs::read_to_string(path)?;
+    let terms = parse_glossary(&text);
+    if terms.is_empty() {
+        anyhow::bail!("{} 中没有找到术语, 请使用 `- **术语**：词1、词2` 的格式", path.display());
+    }
+
+    let tokenizer = Tokenizer::new();
+    let dict = terms.iter()
+        .map(|term| format!("{} {} n\n", term, tokenizer.suggest_freq(term)))
+        .collect::<String>();
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| default_output(path));
+    fs::write(&output, dict)?;
+
+    println!("已从 {} 提取 {} 个术语, 写入 {}", path.display(), terms.len(), output.display());
+    println!("请将该文件加入配置中的 user_dicts 后生效");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_glossary() {
+        let glossary = "# 术语表\n## 市场\n\
+            - **市场指标**：营收、毛利率、归母净 利润\n\
+            - **存储芯片** ：DRAM(DDR4/DDR5)、Flash(MLC/SLC)\n\
+            - **IDM厂商**：英飞凌、意法半导体等\n\
+            - **封装技术**：传统封装、先进封装(如FCBGA芯片封装技术)\n\
+            - **如意**：如来、吉祥如意\n";
+        let terms = parse_glossary(glossary);
+
+        assert!(terms.contains(&"归母净利润".to_string()));
+        assert!(terms.contains(&"存储芯片".to_string()));
+        assert!(terms.contains(&"意法半导体".to_string()));
+        assert!(terms.contains(&"FCBGA芯片封装技术".to_string()));
+        assert!(!terms.contains(&"DDR5".to_string()));
+        assert!(terms.contains(&"如意".to_string()));
+        assert!(terms.contains(&"如来".to_string()));
+        assert!(!terms.iter().any(|t| t.contains('*') || t.ends_with('等')));
+    }
+}
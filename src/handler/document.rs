@@ -1,46 +1,308 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use glob::{MatchOptions, Pattern};
+use serde_json::{Map, Value};
 use walkdir::DirEntry;
-use crate::document::{process_document, chunk::chunk_document};
+use crate::document::{process_document, chunk::chunk_document, dedup::MinHashIndex};
 use crate::vector_store::VectorStore;
 
+const IGNORE_FILE: &str = ".docsterignore";
+// 由入库流程写入的切块与集合元数据, 不允许通过 --meta 或 sidecar 覆盖
+const RESERVED_KEYS: [&str; 6] = ["source", "timestamp", "page", "embedding_model", "embedding_dim", "description"];
+
+fn check_key(key: &str) -> Result<(), String> {
+    if RESERVED_KEYS.contains(&key) || key.starts_with("hnsw:") {
+        return Err(format!("'{}' 是保留的元数据字段, 不能手动设置", key));
+    }
+    Ok(())
+}
+
+pub struct AddOptions {
+    pub recursive: bool,
+    pub chunk_size: usize,
+    pub filter: EntryFilter,
+    pub metadata: Map<String, Value>,
+    pub sidecar: bool,
+    pub dedup: Option<DedupMode>,
+    pub dedup_threshold: Option<f32>,
+}
+
+/// 入库时的去重方式
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum DedupMode {
+    /// 与集合中已有切块的向量余弦相似度达到阈值则丢弃
+    Embedding,
+    /// 文本MinHash估计的Jaccard相似度达到阈值则丢弃
+    Minhash,
+}
+
+impl DedupMode {
+    fn default_threshold(&self) -> f32 {
+        match self {
+            DedupMode::Embedding => 0.95,
+            DedupMode::Minhash => 0.8,
+        }
+    }
+}
+
+/// 目录遍历时的文件过滤规则
+#[derive(Clone)]
+pub struct EntryFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    // 各级目录下 `.docsterignore` 的规则, 以所在目录(相对路径)为作用范围
+    ignored: Vec<(PathBuf, Vec<Pattern>)>,
+    max_file_size: Option<u64>,
+}
+
+impl EntryFilter {
+    pub fn new(include: &[String], exclude: &[String], max_file_size: Option<u64>) -> anyhow::Result<Self> {
+        Ok(Self {
+            include: compile_patterns(include)?,
+            exclude: compile_patterns(exclude)?,
+            ignored: Vec::new(),
+            max_file_size,
+        })
+    }
+
+    /// 读取 `root` 及将要遍历的子目录下的 `.docsterignore`, 每行一个glob, `#` 开头为注释,
+    /// 规则只作用于所在目录, 匹配相对于该目录的路径
+    fn with_ignore_files(mut self, root: &Path, recursive: bool) -> anyhow::Result<Self> {
+        let walker = match recursive {
+            true => walkdir::WalkDir::new(root),
+            false => walkdir::WalkDir::new(root).max_depth(1),
+        };
+        let ignore_files = walker.into_iter()
+            .filter_entry(|e| {
+                let relative = e.path().strip_prefix(root).unwrap_or(e.path());
+                e.depth() == 0 || !self.exclude.iter().any(|pattern| matches(pattern, relative, true))
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && e.file_name() == IGNORE_FILE)
+            .collect::<Vec<DirEntry>>();
+        for entry in ignore_files {
+            let content = std::fs::read_to_string(entry.path())?;
+            let lines = content.lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| line.trim_end_matches('/').to_string())
+                .collect::<Vec<String>>();
+            let dir = entry.path().parent().unwrap_or(root);
+            let dir = dir.strip_prefix(root).unwrap_or(dir).to_path_buf();
+            self.ignored.push((dir, compile_patterns(&lines)?));
+        }
+        Ok(self)
+    }
+
+    fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude.iter().any(|pattern| matches(pattern, relative, true))
+            || self.ignored.iter().any(|(dir, patterns)| match relative.strip_prefix(dir) {
+                Ok(relative) if !relative.as_os_str().is_empty() => {
+                    patterns.iter().any(|pattern| matches(pattern, relative, true))
+                },
+                _ => false,
+            })
+    }
+
+    fn is_included(&self, relative: &Path) -> bool {
+        self.include.is_empty()
+            || self.include.iter().any(|pattern| matches(pattern, relative, false))
+    }
+
+    fn is_too_large(&self, path: &Path) -> bool {
+        match (self.max_file_size, std::fs::metadata(path)) {
+            (Some(limit), Ok(meta)) => meta.len() > limit,
+            _ => false,
+        }
+    }
+}
+
+fn compile_patterns(patterns: &[String]) -> anyhow::Result<Vec<Pattern>> {
+    patterns.iter()
+        .map(|p| Pattern::new(p.trim_start_matches('/'))
+            .map_err(|err| anyhow::anyhow!("无效的匹配规则 '{}': {}", p, err)))
+        .collect()
+}
+
+// 含有'/'的规则匹配相对路径, 否则只匹配文件名(排除规则还会匹配每一级目录名)
+fn matches(pattern: &Pattern, relative: &Path, any_component: bool) -> bool {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    if pattern.as_str().contains('/') {
+        return pattern.matches_path_with(relative, options);
+    }
+    if any_component {
+        relative.components()
+            .any(|c| pattern.matches_with(&c.as_os_str().to_string_lossy(), options))
+    } else {
+        relative.file_name()
+            .map(|name| pattern.matches_with(&name.to_string_lossy(), options))
+            .unwrap_or(false)
+    }
+}
+
+/// 解析文件大小, 支持 `1024`, `512K`, `20MB`, `1G` 等写法
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("无效的文件大小: {}", s))?;
+    let factor = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        other => return Err(format!("未知的单位: {}", other)),
+    };
+    number.checked_mul(factor).ok_or_else(|| format!("文件大小超出范围: {}", s))
+}
+
+/// 解析 `key=value` 形式的元数据, 数字与布尔值保留原类型以便过滤
+pub fn parse_meta(s: &str) -> Result<(String, Value), String> {
+    let (key, value) = s.split_once('=')
+        .ok_or_else(|| format!("元数据格式应为 key=value: {}", s))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("元数据的键不能为空: {}", s));
+    }
+    check_key(key)?;
+    let value = value.trim();
+    let value = if let Ok(n) = value.parse::<i64>() {
+        Value::from(n)
+    } else if let Ok(f) = value.parse::<f64>() {
+        Value::from(f)
+    } else if let Ok(b) = value.parse::<bool>() {
+        Value::from(b)
+    } else {
+        Value::from(value)
+    };
+    Ok((key.to_string(), value))
+}
+
+// 读取 `<file>.meta.json`, 只接受字符串、数字与布尔值
+fn read_sidecar(path: &Path) -> anyhow::Result<Map<String, Value>> {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".meta.json");
+    let sidecar = PathBuf::from(sidecar);
+    if !sidecar.is_file() {
+        return Ok(Map::new());
+    }
+
+    let content = std::fs::read_to_string(&sidecar)?;
+    let metadata: Map<String, Value> = serde_json::from_str(&content)
+        .map_err(|err| anyhow::anyhow!("无法解析 {}: {}", sidecar.display(), err))?;
+    if let Some((key, _)) = metadata.iter()
+        .find(|(_, v)| !(v.is_string() || v.is_number() || v.is_boolean())) {
+        anyhow::bail!("{} 中的 '{}' 不是字符串、数字或布尔值", sidecar.display(), key);
+    }
+    for key in metadata.keys() {
+        check_key(key).map_err(|err| anyhow::anyhow!("{}: {}", sidecar.display(), err))?;
+    }
+    Ok(metadata)
+}
+
 pub async fn add_documents(
     store: &VectorStore,
     path: PathBuf,
     name: &str,
-    recursive: bool,
-    chunk_size: usize
+    options: AddOptions,
 ) -> anyhow::Result<()> {
     println!("正在处理文档: {}", path.display());
 
+    // 去重需要读取集合, 先打开(不存在时创建)目标集合
+    if options.dedup.is_some() {
+        let coll_metadata = (!options.metadata.is_empty()).then(|| options.metadata.clone());
+        store.open_collection(name, coll_metadata, None).await?;
+    }
+    let mut minhash = match options.dedup {
+        Some(mode @ DedupMode::Minhash) => {
+            let threshold = options.dedup_threshold.unwrap_or(mode.default_threshold());
+            Some(load_minhash_index(store, name, threshold).await?)
+        },
+        _ => None,
+    };
+
+    let mut dropped = 0;
+    for file in document_files(&path, &options)? {
+        dropped += process_single_file(store, file, name, &options, minhash.as_mut()).await?;
+    }
+    if options.dedup.is_some() {
+        println!("去重完成, 共丢弃{}块重复内容", dropped);
+    }
+    Ok(())
+}
+
+async fn load_minhash_index(store: &VectorStore, name: &str, threshold: f32) -> anyhow::Result<MinHashIndex> {
+    let mut index = MinHashIndex::new(threshold);
+    let mut offset: usize = 0;
+    let limit: usize = 1000;
+    loop {
+        let page = store.get_page(name, offset, limit, &["documents"]).await?;
+        if page.ids.is_empty() { break }
+        let documents = page.documents.unwrap_or_default();
+        for (id, doc) in page.ids.iter().zip(documents) {
+            if let Some(doc) = doc {
+                index.insert(id, &doc);
+            }
+        }
+        offset += limit;
+    }
+    Ok(index)
+}
+
+fn retain<T>(items: &mut Vec<T>, keep: &[bool]) {
+    let mut keep = keep.iter();
+    items.retain(|_| *keep.next().unwrap_or(&true));
+}
+
+/// 待处理的文件列表, 目录会按过滤规则展开, 单个文件同样按 --include/--exclude 与大小限制过滤
+pub fn document_files(path: &PathBuf, options: &AddOptions) -> anyhow::Result<Vec<PathBuf>> {
     if path.is_dir() {
-        process_directory(store, path, name, recursive, chunk_size).await
-    } else {
-        process_single_file(store, path, name, chunk_size).await
+        return collect_files(path, options.recursive, &options.filter);
+    }
+    let filter = &options.filter;
+    let name = Path::new(path.file_name().unwrap_or(path.as_os_str()));
+    if filter.is_excluded(name) || !filter.is_included(name) {
+        println!("警告: 文件不符合过滤规则, 已跳过: {}", path.display());
+        return Ok(Vec::new());
     }
+    if filter.is_too_large(path) {
+        println!("警告: 跳过超过大小限制的文件: {}", path.display());
+        return Ok(Vec::new());
+    }
+    Ok(vec![path.clone()])
 }
 
-async fn process_directory(
-    store: &VectorStore,
-    path: PathBuf,
-    name: &str,
-    recursive: bool,
-    chunk_size: usize
-) -> anyhow::Result<()> {
-    let entries = get_entries(&path, recursive);
-    
+fn collect_files(path: &PathBuf, recursive: bool, filter: &EntryFilter) -> anyhow::Result<Vec<PathBuf>> {
+    let filter = filter.clone().with_ignore_files(path, recursive)?;
+    let entries = get_entries(path, recursive, &filter);
+    let mut files = Vec::new();
+
     for entry in entries {
         let entry_path = entry.path();
         if entry_path.is_file() {
             if let Some(ext) = entry_path.extension().and_then(|e| e.to_str()) {
                 match ext {
-                    "pdf" | "docx" => {
-                        process_single_file(store, entry_path.to_path_buf(), name, chunk_size).await?;
-                    },
+                    "pdf" | "docx" => files.push(entry_path.to_path_buf()),
                     _ => println!("警告: 跳过不支持的文件类型: {}", entry_path.display()),
                 }
             }
         }
     }
+    Ok(files)
+}
+
+pub fn list_documents(path: &PathBuf, options: &AddOptions) -> anyhow::Result<()> {
+    let files = document_files(path, options)?;
+    println!("\n将要处理的文件：");
+    let mut total = 0;
+    for file in &files {
+        let size = std::fs::metadata(file)?.len();
+        total += size;
+        println!("\t{} ({} KB)", file.display(), size / 1024);
+    }
+    println!("共 {} 个文件, {} KB", files.len(), total / 1024);
     Ok(())
 }
 
@@ -48,55 +310,138 @@ async fn process_single_file(
     store: &VectorStore,
     path: PathBuf,
     name: &str,
-    chunk_size: usize
-) -> anyhow::Result<()> {
+    options: &AddOptions,
+    minhash: Option<&mut MinHashIndex>,
+) -> anyhow::Result<usize> {
     let file_stem = path.file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("unknown");
     
     println!("正在处理文档: {}", path.display());
     
-    let content = process_document(&path)?;
-    let chunks = chunk_document(content, chunk_size);
+    let (content, document_metadata) = process_document(&path)?;
+    let chunks = chunk_document(content, options.chunk_size);
 
     println!("文档 {} 切块完成, 共分成{}块", path.display(), chunks.len());
     
+    let mut metadata = document_metadata.to_chunk_metadata();
+    metadata.extend(options.metadata.clone());
+    if options.sidecar {
+        metadata.extend(read_sidecar(&path)?);
+    }
+
     let mut doc_ids = Vec::new();
     let mut texts = Vec::new();
+    let mut metadatas = Vec::new();
     
     for (i, chunk) in chunks.into_iter().enumerate() {
         doc_ids.push(format!("{}-{}", file_stem, i));
         texts.push(chunk.content);
+        let mut metadata = metadata.clone();
+        if let Some(page) = document_metadata.page_at(chunk.start) {
+            metadata.insert("page".to_string(), Value::from(page));
+        }
+        metadatas.push(metadata);
     }
     
-    store.add(
-        name,
-        doc_ids.iter().map(|s| s.as_str()).collect(),
-        texts.iter().map(|s| s.as_str()).collect(),
-        None,
-    ).await?;
+    let mut ids = doc_ids.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+    let mut docs = texts.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
 
-    Ok(())
+    if let Some(index) = minhash {
+        let keep = ids.iter().zip(&docs).map(|(id, doc)| {
+            let duplicate = index.is_duplicate(id, doc);
+            if !duplicate {
+                index.insert(id, doc);
+            }
+            !duplicate
+        }).collect::<Vec<bool>>();
+        retain(&mut ids, &keep);
+        retain(&mut docs, &keep);
+        retain(&mut metadatas, &keep);
+    }
+
+    let mut embeddings = None;
+    if let Some(mode @ DedupMode::Embedding) = options.dedup {
+        let threshold = options.dedup_threshold.unwrap_or(mode.default_threshold());
+        let mut embeds = store.embed_for(name, &docs).await?;
+        let keep = store.near_duplicates(name, &ids, &embeds, threshold).await?
+            .iter().map(|duplicate| !duplicate).collect::<Vec<bool>>();
+        retain(&mut ids, &keep);
+        retain(&mut docs, &keep);
+        retain(&mut metadatas, &keep);
+        retain(&mut embeds, &keep);
+        embeddings = Some(embeds);
+    }
+
+    let dropped = doc_ids.len() - ids.len();
+    if dropped > 0 {
+        println!("文档 {} 丢弃了{}块重复内容", path.display(), dropped);
+    }
+    if ids.is_empty() {
+        return Ok(dropped);
+    }
+
+    let metadatas = Some(metadatas);
+    let coll_metadata = (!options.metadata.is_empty()).then(|| options.metadata.clone());
+    match embeddings {
+        Some(embeddings) => store.upsert(name, ids, docs, embeddings, metadatas, coll_metadata).await?,
+        None => store.add(name, ids, docs, metadatas, coll_metadata).await?,
+    }
+
+    Ok(dropped)
 }
 
-fn get_entries(path: &PathBuf, recursive: bool) -> Box<dyn Iterator<Item = DirEntry>> {
+fn get_entries<'a>(
+    path: &'a PathBuf,
+    recursive: bool,
+    filter: &'a EntryFilter
+) -> Box<dyn Iterator<Item = DirEntry> + 'a> {
     let iter = if recursive {
         walkdir::WalkDir::new(path)
     } else {
         walkdir::WalkDir::new(path).max_depth(1)
     };
-    Box::new(iter.into_iter().filter_map(|e| e.ok()))
+    let iter = iter.into_iter()
+        .filter_entry(move |e| {
+            let relative = e.path().strip_prefix(path).unwrap_or(e.path());
+            e.depth() == 0 || !filter.is_excluded(relative)
+        })
+        .filter_map(|e| e.ok())
+        .filter(move |e| {
+            if !e.file_type().is_file() {
+                return true;
+            }
+            let relative = e.path().strip_prefix(path).unwrap_or(e.path());
+            if !filter.is_included(relative) {
+                return false;
+            }
+            if filter.is_too_large(e.path()) {
+                println!("警告: 跳过超过大小限制的文件: {}", e.path().display());
+                return false;
+            }
+            true
+        });
+    Box::new(iter)
 }
 
 pub async fn list_collections(store: &VectorStore) -> anyhow::Result<()> {
     let collections = store.list_collections().await?;
     println!("\n所有的集合：");
     for coll in collections {
-        println!("\t{}", coll.name());
+        match coll.metadata().and_then(|m| m.get("description")).and_then(|d| d.as_str()) {
+            Some(description) => println!("\t{}: {}", coll.name(), description),
+            None => println!("\t{}", coll.name()),
+        }
     }
     Ok(())
 }
 
+pub async fn describe_collection(store: &VectorStore, name: &str, description: &str) -> anyhow::Result<()> {
+    store.set_description(name, description).await?;
+    println!("已更新集合 {} 的描述", name);
+    Ok(())
+}
+
 pub async fn remove_collection(store: &VectorStore, name: &str) -> anyhow::Result<()> {
     store.delete_collection(name).await?;
     Ok(())
@@ -107,3 +452,81 @@ pub async fn clean_collections(store: &VectorStore) -> anyhow::Result<()> {
     println!("已清空");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("20MB"), Ok(20 * 1024 * 1024));
+        assert!(parse_size("10TB").is_err());
+        assert!(parse_size("99999999999999GB").is_err());
+    }
+
+    #[test]
+    fn test_parse_meta() {
+        assert_eq!(parse_meta("department=finance"), Ok(("department".to_string(), Value::from("finance"))));
+        assert_eq!(parse_meta("year=2023"), Ok(("year".to_string(), Value::from(2023))));
+        assert_eq!(parse_meta("public = false"), Ok(("public".to_string(), Value::from(false))));
+        assert!(parse_meta("year").is_err());
+        assert!(parse_meta("=2023").is_err());
+        assert!(parse_meta("source=other.pdf").is_err());
+        assert!(parse_meta("embedding_model=x").is_err());
+        assert!(parse_meta("hnsw:space=l2").is_err());
+    }
+
+    #[test]
+    fn test_entry_filter() -> anyhow::Result<()> {
+        let filter = EntryFilter::new(
+            &["*.pdf".to_string()],
+            &["drafts".to_string(), "tmp/*.pdf".to_string()],
+            None,
+        )?;
+        assert!(filter.is_included(Path::new("report/a.pdf")));
+        assert!(!filter.is_included(Path::new("report/a.docx")));
+        assert!(filter.is_excluded(Path::new("report/drafts")));
+        assert!(filter.is_excluded(Path::new("tmp/a.pdf")));
+        assert!(!filter.is_excluded(Path::new("report/tmp/a.pdf")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_files() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join(format!("docster-files-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub"))?;
+        for file in ["a.pdf", "b.pdf", "sub/b.pdf", "sub/c.pdf"] {
+            std::fs::write(root.join(file), "x")?;
+        }
+        std::fs::write(root.join("sub").join(IGNORE_FILE), "# 只作用于sub\nb.pdf\n")?;
+        let options = |include: &[&str], max_file_size| -> anyhow::Result<AddOptions> {
+            let include = include.iter().map(|p| p.to_string()).collect::<Vec<String>>();
+            Ok(AddOptions {
+                recursive: true,
+                chunk_size: 300,
+                filter: EntryFilter::new(&include, &[], max_file_size)?,
+                metadata: Map::new(),
+                sidecar: false,
+                dedup: None,
+                dedup_threshold: None,
+            })
+        };
+
+        let mut files = document_files(&root, &options(&[], None)?)?;
+        files.sort();
+        let relative = files.iter()
+            .map(|f| f.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect::<Vec<String>>();
+        assert_eq!(relative, vec!["a.pdf", "b.pdf", "sub/c.pdf"]);
+
+        let file = root.join("a.pdf");
+        assert_eq!(document_files(&file, &options(&[], None)?)?, vec![file.clone()]);
+        assert!(document_files(&file, &options(&["*.docx"], None)?)?.is_empty());
+        assert!(document_files(&file, &options(&[], Some(0))?)?.is_empty());
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::document::{process_document, chunk::chunk_document};
+use super::document::{document_files, AddOptions};
+
+// 少于该字符数的文件视为空文件, 通常是扫描件或提取失败
+const NEAR_EMPTY_CHARS: usize = 100;
+const HISTOGRAM_BUCKETS: usize = 10;
+
+#[derive(Serialize)]
+struct FileReport {
+    path: PathBuf,
+    chars: usize,
+    chunks: usize,
+    tokens: usize,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Bucket {
+    min: usize,
+    max: usize,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct DryRunReport {
+    files: Vec<FileReport>,
+    histogram: Vec<Bucket>,
+    near_empty: Vec<PathBuf>,
+    total_chars: usize,
+    total_chunks: usize,
+    estimated_tokens: usize,
+    estimated_cost: f64,
+}
+
+/// 粗略估算token数: 中日韩字符各计1个, 其余字符每4个计1个
+pub fn estimate_tokens(text: &str) -> usize {
+    let (cjk, other) = text.chars()
+        .filter(|c| !c.is_whitespace())
+        .fold((0usize, 0usize), |(cjk, other), c| {
+            if is_cjk(c) { (cjk + 1, other) } else { (cjk, other + 1) }
+        });
+    cjk + other.div_ceil(4)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3000..=0x303F | 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xFF00..=0xFFEF)
+}
+
+/// 只执行提取与切块, 输出统计报告而不调用向量模型
+pub fn dry_run_documents(
+    path: &PathBuf,
+    options: &AddOptions,
+    report_path: Option<&Path>,
+    price: f64,
+) -> anyhow::Result<()> {
+    let width = options.chunk_size.div_ceil(HISTOGRAM_BUCKETS).max(1);
+    let mut histogram = (0..HISTOGRAM_BUCKETS)
+        .map(|i| Bucket { min: i * width + 1, max: (i + 1) * width, count: 0 })
+        .collect::<Vec<Bucket>>();
+    let mut files = Vec::new();
+
+    for file in document_files(path, options)? {
+        let (content, _) = match process_document(&file) {
+            Ok(res) => res,
+            Err(err) => {
+                files.push(FileReport { path: file, chars: 0, chunks: 0, tokens: 0, error: Some(err.to_string()) });
+                continue;
+            }
+        };
+        let chars = content.chars().count();
+        let tokens = estimate_tokens(&content);
+        let chunks = chunk_document(content, options.chunk_size);
+        for chunk in &chunks {
+            let len = chunk.content.chars().count();
+            let bucket = (len.saturating_sub(1) / width).min(HISTOGRAM_BUCKETS - 1);
+            histogram[bucket].count += 1;
+        }
+        files.push(FileReport { path: file, chars, chunks: chunks.len(), tokens, error: None });
+    }
+
+    let estimated_tokens = files.iter().map(|f| f.tokens).sum::<usize>();
+    let report = DryRunReport {
+        near_empty: files.iter()
+            .filter(|f| f.error.is_none() && f.chars < NEAR_EMPTY_CHARS)
+            .map(|f| f.path.clone())
+            .collect(),
+        total_chars: files.iter().map(|f| f.chars).sum(),
+        total_chunks: files.iter().map(|f| f.chunks).sum(),
+        estimated_tokens,
+        estimated_cost: estimated_tokens as f64 / 1000.0 * price,
+        files,
+        histogram,
+    };
+
+    match report_path {
+        Some(report_path) => {
+            std::fs::write(report_path, serde_json::to_string_pretty(&report)?)?;
+            println!("报告已写入: {}", report_path.display());
+        },
+        None => print_report(&report),
+    }
+    Ok(())
+}
+
+fn print_report(report: &DryRunReport) {
+    println!("\n文件统计：");
+    for file in &report.files {
+        match &file.error {
+            Some(err) => println!("\t{} 提取失败: {}", file.path.display(), err),
+            None => println!("\t{} 字符数: {}, 切块数: {}", file.path.display(), file.chars, file.chunks),
+        }
+    }
+
+    println!("\n切块长度分布：");
+    let peak = report.histogram.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    for bucket in &report.histogram {
+        let bar = "#".repeat(bucket.count * 40 / peak);
+        println!("\t{:>5}-{:<5} {:>6} {}", bucket.min, bucket.max, bucket.count, bar);
+    }
+
+    if !report.near_empty.is_empty() {
+        println!("\n内容过少的文件(少于{}字符)：", NEAR_EMPTY_CHARS);
+        for path in &report.near_empty {
+            println!("\t{}", path.display());
+        }
+    }
+
+    println!(
+        "\n共 {} 个文件, {} 字符, {} 块, 预计 {} tokens, 预计费用 {:.4}",
+        report.files.len(), report.total_chars, report.total_chunks,
+        report.estimated_tokens, report.estimated_cost,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens("产能利用率"), 5);
+        assert_eq!(estimate_tokens("DDR5 FCBGA"), 3);
+        assert_eq!(estimate_tokens(""), 0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use serde_json::{json, Map, Value};
+use crate::vector_store::VectorStore;
+
+// 按ID末尾的切块序号排序, 如 report-12
+fn chunk_index(id: &str) -> usize {
+    id.rsplit('-').next().and_then(|n| n.parse().ok()).unwrap_or(usize::MAX)
+}
+
+fn format_metadata(metadata: Option<&Map<String, Value>>) -> String {
+    match metadata {
+        Some(metadata) => metadata.iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect::<Vec<String>>()
+            .join(", "),
+        None => "无".to_string(),
+    }
+}
+
+pub async fn show_chunk(store: &VectorStore, name: &str, id: &str) -> anyhow::Result<()> {
+    let result = store.get_by_ids(
+        name, vec![id.to_string()], &["documents", "metadatas", "embeddings"]
+    ).await?;
+    if result.ids.is_empty() {
+        anyhow::bail!("集合 {} 中不存在切块 {}", name, id);
+    }
+
+    let document = result.documents.and_then(|docs| docs.into_iter().next().flatten()).unwrap_or_default();
+    let metadata = result.metadatas.and_then(|metas| metas.into_iter().next().flatten());
+    let dim = result.embeddings
+        .and_then(|embeds| embeds.into_iter().next().flatten())
+        .map(|embed| embed.len())
+        .unwrap_or(0);
+
+    println!("\nID: {}", id);
+    println!("元数据: {}", format_metadata(metadata.as_ref()));
+    println!("向量维度: {}", dim);
+    println!("长度: {} 字符\n", document.chars().count());
+    println!("{}", document);
+    Ok(())
+}
+
+pub async fn list_chunks(store: &VectorStore, name: &str, source: &str) -> anyhow::Result<()> {
+    let result = store.get_where(name, json!({ "source": source }), &["documents"]).await?;
+    if result.ids.is_empty() {
+        anyhow::bail!("集合 {} 中没有来源为 {} 的切块, 可通过 doc stats 查看记录的来源", name, source);
+    }
+
+    let documents = result.documents.unwrap_or_default();
+    let mut chunks = result.ids.into_iter().zip(documents).collect::<Vec<_>>();
+    chunks.sort_by_key(|(id, _)| chunk_index(id));
+
+    println!("\n{} 共 {} 块：", source, chunks.len());
+    for (id, doc) in chunks {
+        let doc = doc.unwrap_or_default();
+        println!("\n===[{}] {} 字符===\n{}", id, doc.chars().count(), doc);
+    }
+    Ok(())
+}
+
+pub async fn collection_stats(store: &VectorStore, name: &str) -> anyhow::Result<()> {
+    let count = store.count(name).await?;
+    let coll_metadata = store.collection_metadata(name).await?;
+
+    let mut sources: BTreeMap<String, usize> = BTreeMap::new();
+    let mut total_chars = 0;
+    let mut dim = 0;
+
+    let mut offset: usize = 0;
+    let limit: usize = 1000;
+    loop {
+        let include: &[&str] = if offset == 0 {
+            &["documents", "metadatas", "embeddings"]
+        } else {
+            &["documents", "metadatas"]
+        };
+        let page = store.get_page(name, offset, limit, include).await?;
+        if page.ids.is_empty() { break }
+
+        if let Some(embed) = page.embeddings.iter().flatten().flatten().next() {
+            dim = embed.len();
+        }
+        for doc in page.documents.iter().flatten().flatten() {
+            total_chars += doc.chars().count();
+        }
+        let metadatas = page.metadatas.unwrap_or_else(|| vec![None; page.ids.len()]);
+        for metadata in metadatas {
+            let source = metadata.as_ref()
+                .and_then(|m| m.get("source"))
+                .and_then(|s| s.as_str())
+                .unwrap_or("未知来源");
+            *sources.entry(source.to_string()).or_default() += 1;
+        }
+        offset += limit;
+    }
+
+    println!("\n集合: {}", name);
+    println!("集合元数据: {}", format_metadata(coll_metadata.as_ref()));
+    println!("文档数: {}", sources.len());
+    println!("切块数: {}", count);
+    println!("平均长度: {} 字符", total_chars.checked_div(count).unwrap_or(0));
+    println!("向量维度: {}", dim);
+    println!("\n来源：");
+    for (source, chunks) in sources {
+        println!("\t{} ({}块)", source, chunks);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_index() {
+        assert_eq!(chunk_index("2023-annual-report-12"), 12);
+        assert_eq!(chunk_index("report"), usize::MAX);
+    }
+}
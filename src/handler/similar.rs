@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::document::{process_document, chunk::chunk_document};
+use crate::vector_store::{similarity, VectorStore};
+
+// 每次查询的向量数
+const QUERY_BATCH: usize = 32;
+// 每个查询向量召回 limit 的倍数个近邻
+const NEIGHBORS_PER_DOC: usize = 5;
+
+/// 与输入相似的一篇文档
+#[derive(Debug, PartialEq)]
+pub struct SimilarDocument {
+    pub source: String,
+    pub score: f32,
+    pub best_chunk: String,
+    pub matched: usize,
+}
+
+/// 按来源汇总近邻: 每个查询切块取该来源最相近切块的相似度, 再对所有查询切块求平均,
+/// 即覆盖输入越多、越相近的文档排在越前面. `exclude` 为输入自身的来源
+fn rank_sources(rows: &[Vec<(String, String, f32)>], exclude: Option<&str>) -> Vec<SimilarDocument> {
+    let mut documents: HashMap<&str, SimilarDocument> = HashMap::new();
+    let mut best_scores: HashMap<&str, f32> = HashMap::new();
+    for row in rows {
+        let mut best: HashMap<&str, (&str, f32)> = HashMap::new();
+        for (source, id, score) in row {
+            if exclude == Some(source.as_str()) {
+                continue;
+            }
+            let entry = best.entry(source.as_str()).or_insert((id.as_str(), *score));
+            if *score > entry.1 {
+                *entry = (id.as_str(), *score);
+            }
+        }
+        for (source, (id, score)) in best {
+            let document = documents.entry(source).or_insert(SimilarDocument {
+                source: source.to_string(),
+                score: 0.0,
+                best_chunk: id.to_string(),
+                matched: 0,
+            });
+            let best_score = best_scores.entry(source).or_insert(score);
+            if score > *best_score {
+                *best_score = score;
+                document.best_chunk = id.to_string();
+            }
+            document.score += score;
+            document.matched += 1;
+        }
+    }
+
+    let mut documents = documents.into_values()
+        .map(|mut document| {
+            document.score /= rows.len().max(1) as f32;
+            document
+        })
+        .collect::<Vec<SimilarDocument>>();
+    documents.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.source.cmp(&b.source)));
+    documents
+}
+
+/// 查找与文件或已入库切块相似的文档, `file` 会按 `chunk_size` 切块后计算向量,
+/// `id` 直接使用集合中保存的向量
+pub async fn similar_documents(
+    store: &VectorStore,
+    name: &str,
+    file: Option<&Path>,
+    id: Option<&str>,
+    chunk_size: usize,
+    limit: usize,
+) -> anyhow::Result<()> {
+    let (label, embeddings, exclude, exclude_id) = match (file, id) {
+        (Some(path), _) => {
+            let (content, metadata) = process_document(path)?;
+            let chunks = chunk_document(content, chunk_size);
+            let texts = chunks.iter().map(|chunk| chunk.content.as_str()).collect::<Vec<&str>>();
+            println!("文件 {} 共 {} 块, 正在计算向量...", path.display(), texts.len());
+            let embeddings = store.embed_for(name, &texts).await?;
+            (path.display().to_string(), embeddings, Some(metadata.path.display().to_string()), None)
+        },
+        (None, Some(id)) => {
+            let result = store.get_by_ids(name, vec![id.to_string()], &["metadatas", "embeddings"]).await?;
+            let embedding = result.embeddings.and_then(|embeds| embeds.into_iter().next().flatten())
+                .ok_or_else(|| anyhow::anyhow!("集合 {} 中不存在切块 {}", name, id))?;
+            let source = result.metadatas
+                .and_then(|metas| metas.into_iter().next().flatten())
+                .and_then(|meta| meta.get("source").and_then(|s| s.as_str()).map(|s| s.to_string()));
+            (id.to_string(), vec![embedding], source, Some(id))
+        },
+        (None, None) => anyhow::bail!("请通过 --file 或 --id 指定要比较的内容"),
+    };
+
+    let mut rows = Vec::with_capacity(embeddings.len());
+    for batch in embeddings.chunks(QUERY_BATCH) {
+        let result = store.query_embedding(name, batch.to_vec(), limit * NEIGHBORS_PER_DOC).await?;
+        let metadatas = result.metadatas.unwrap_or_default();
+        let distances = result.distances.unwrap_or_default();
+        for (row, ids) in result.ids.into_iter().enumerate() {
+            rows.push(ids.into_iter().enumerate()
+                .filter(|(_, hit)| exclude_id != Some(hit.as_str()))
+                .map(|(i, hit)| {
+                    let source = metadatas.get(row).and_then(|m| m.get(i)).cloned().flatten()
+                        .and_then(|meta| meta.get("source").and_then(|s| s.as_str()).map(|s| s.to_string()))
+                        .unwrap_or_else(|| hit.clone());
+                    let distance = distances.get(row).and_then(|d| d.get(i)).copied().unwrap_or(1.0);
+                    (source, hit, similarity(distance))
+                })
+                .collect());
+        }
+    }
+
+    let documents = rank_sources(&rows, exclude.as_deref());
+    if documents.is_empty() {
+        println!("\n集合 {} 中没有找到与 {} 相似的文档", name, label);
+        return Ok(());
+    }
+    println!("\n与 {} 相似的文档:", label);
+    for (i, document) in documents.iter().take(limit).enumerate() {
+        println!(
+            "{:>3}. {}\n     相似度: {:.3}, 匹配切块: {}/{}, 最相近的切块: {}",
+            i + 1, document.source, document.score, document.matched, rows.len(), document.best_chunk
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(source: &str, id: &str, score: f32) -> (String, String, f32) {
+        (source.to_string(), id.to_string(), score)
+    }
+
+    #[test]
+    fn test_rank_sources() {
+        let rows = vec![
+            vec![hit("a.pdf", "a-0", 0.9), hit("a.pdf", "a-1", 0.7), hit("b.pdf", "b-0", 0.8), hit("self.pdf", "s-0", 1.0)],
+            vec![hit("b.pdf", "b-3", 0.4), hit("a.pdf", "a-2", 0.5)],
+        ];
+        let ranked = rank_sources(&rows, Some("self.pdf"));
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].source, "a.pdf");
+        assert!((ranked[0].score - 0.7).abs() < 1e-6);
+        assert_eq!(ranked[0].best_chunk, "a-0");
+        assert_eq!(ranked[1].source, "b.pdf");
+        assert_eq!(ranked[1].matched, 2);
+    }
+}
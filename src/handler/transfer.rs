@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+use serde_json::Value;
+use crate::embedding::{self, EmbeddingProvider};
+use crate::vector_store::VectorStore;
+use crate::Config;
+
+const PAGE_SIZE: usize = 500;
+// 重命名后的ID仍然冲突时, 追加序号的最大尝试次数
+const RENAME_ATTEMPTS: usize = 100;
+
+/// 目标集合中已存在相同ID时的处理方式
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ConflictPolicy {
+    /// 保留目标集合中的记录
+    Skip,
+    /// 用来源集合的记录覆盖
+    Overwrite,
+    /// 以 `<来源集合>.<ID>` 作为新ID写入, 仍然冲突时追加序号
+    Rename,
+    /// 遇到冲突时中止
+    Fail,
+}
+
+#[derive(Default)]
+struct TransferStats {
+    copied: usize,
+    skipped: usize,
+    renamed: usize,
+}
+
+// 为冲突的记录生成新ID: `<来源集合>.<ID>`, 与目标集合或 `taken` 中的ID仍然冲突时追加序号
+async fn rename_ids(
+    store: &VectorStore,
+    from: &str,
+    to: &str,
+    ids: &[String],
+    taken: &HashSet<String>,
+) -> anyhow::Result<Vec<String>> {
+    let mut renamed: Vec<Option<String>> = vec![None; ids.len()];
+    for attempt in 1..=RENAME_ATTEMPTS {
+        let pending = (0..ids.len()).filter(|&i| renamed[i].is_none()).collect::<Vec<usize>>();
+        if pending.is_empty() { break }
+        let candidates = pending.iter()
+            .map(|&i| match attempt {
+                1 => format!("{}.{}", from, ids[i]),
+                n => format!("{}.{}.{}", from, ids[i], n),
+            })
+            .collect::<Vec<String>>();
+        let existing = store.get_by_ids(to, candidates.clone(), &[]).await?.ids
+            .into_iter().collect::<HashSet<String>>();
+        for (i, candidate) in pending.into_iter().zip(candidates) {
+            if !existing.contains(&candidate) && !taken.contains(&candidate) {
+                renamed[i] = Some(candidate);
+            }
+        }
+    }
+    renamed.into_iter().zip(ids)
+        .map(|(new_id, id)| new_id.ok_or_else(|| anyhow::anyhow!("无法为 {} 生成不冲突的新ID", id)))
+        .collect()
+}
+
+// 目标集合不存在时以第一个来源的元数据创建, 已存在时检查每个来源的向量模型与维度
+async fn open_target(store: &VectorStore, sources: &[String], to: &str) -> anyhow::Result<()> {
+    for from in sources {
+        if from == to {
+            anyhow::bail!("来源集合与目标集合不能相同: {}", from);
+        }
+        store.open_target(to, store.collection_metadata(from).await?.as_ref()).await?;
+    }
+    Ok(())
+}
+
+async fn transfer(
+    store: &VectorStore,
+    from: &str,
+    to: &str,
+    policy: ConflictPolicy,
+) -> anyhow::Result<TransferStats> {
+    let total = store.count(from).await?;
+    let source = store.collection_metadata(from).await?;
+
+    let mut stats = TransferStats::default();
+    let mut offset: usize = 0;
+    loop {
+        let records = store.get_records(from, offset, PAGE_SIZE).await?;
+        if records.is_empty() { break }
+        offset += records.len();
+
+        let ids = records.iter().map(|r| r.id.clone()).collect::<Vec<String>>();
+        let existing = store.get_by_ids(to, ids, &[]).await?.ids
+            .into_iter().collect::<HashSet<String>>();
+
+        let mut page = Vec::with_capacity(records.len());
+        let mut conflicts = Vec::new();
+        for record in records {
+            if existing.contains(&record.id) {
+                match policy {
+                    ConflictPolicy::Skip => {
+                        stats.skipped += 1;
+                        continue;
+                    },
+                    ConflictPolicy::Overwrite => {},
+                    ConflictPolicy::Rename => {
+                        conflicts.push(record);
+                        continue;
+                    },
+                    ConflictPolicy::Fail => anyhow::bail!("ID冲突: {} 已存在于集合 {}", record.id, to),
+                }
+            }
+            page.push(record);
+        }
+        if !conflicts.is_empty() {
+            let ids = conflicts.iter().map(|r| r.id.clone()).collect::<Vec<String>>();
+            let taken = page.iter().map(|r| r.id.clone()).collect::<HashSet<String>>();
+            let new_ids = rename_ids(store, from, to, &ids, &taken).await?;
+            for (mut record, id) in conflicts.into_iter().zip(new_ids) {
+                record.id = id;
+                stats.renamed += 1;
+                page.push(record);
+            }
+        }
+
+        stats.copied += page.len();
+        if !page.is_empty() {
+            store.upsert_records(to, page, source.as_ref()).await?;
+        }
+        println!("{} -> {}: {}/{}", from, to, offset, total);
+    }
+    Ok(stats)
+}
+
+fn report(stats: &TransferStats) {
+    println!("写入 {} 条, 跳过 {} 条, 重命名 {} 条", stats.copied, stats.skipped, stats.renamed);
+}
+
+pub async fn rename_collection(store: &VectorStore, from: &str, to: &str) -> anyhow::Result<()> {
+    store.rename_collection(from, to).await?;
+    println!("集合 {} 已重命名为 {}", from, to);
+    Ok(())
+}
+
+pub async fn copy_collection(
+    store: &VectorStore,
+    from: &str,
+    to: &str,
+    policy: ConflictPolicy,
+) -> anyhow::Result<()> {
+    open_target(store, &[from.to_string()], to).await?;
+    let stats = transfer(store, from, to, policy).await?;
+    report(&stats);
+    Ok(())
+}
+
+pub async fn merge_collections(
+    store: &VectorStore,
+    sources: &[String],
+    into: &str,
+    policy: ConflictPolicy,
+    delete_sources: bool,
+) -> anyhow::Result<()> {
+    // 先检查所有来源, 避免合并到一半才发现向量模型不一致
+    open_target(store, sources, into).await?;
+    let mut total = TransferStats::default();
+    for source in sources {
+        let stats = transfer(store, source, into, policy).await?;
+        total.copied += stats.copied;
+        total.skipped += stats.skipped;
+        total.renamed += stats.renamed;
+    }
+    report(&total);
+
+    // 跳过的记录只保存在来源集合中, 删除后将无法找回
+    if delete_sources && total.skipped > 0 {
+        println!("警告: 有 {} 条记录因ID冲突被跳过, 已保留来源集合", total.skipped);
+    } else if delete_sources {
+        for source in sources {
+            store.delete_collection(source).await?;
+            println!("已删除集合 {}", source);
+        }
+    }
+    Ok(())
+}
+
+/// 使用新的向量模型重新计算集合中所有文档的向量, 写入临时集合后替换原集合
+pub async fn reembed_collection(
+    store: &VectorStore,
+    config: &Config,
+    name: &str,
+    model: &str,
+    keep_old: bool,
+) -> anyhow::Result<()> {
+    let client = embedding::with_model(config, model);
+    let batch_size = (config.batch as usize).min(client.max_input());
+
+    let tmp_name = format!("{}-reembed-tmp", name);
+    let old_name = format!("{}-old", name);
+    let total = store.count(name).await?;
+    // 替换时需要这两个名称, 在计算向量之前检查, 避免白白消耗额度
+    let existing = store.list_collections().await?.iter()
+        .map(|c| c.name().to_string())
+        .collect::<HashSet<String>>();
+    for taken in [&tmp_name, &old_name] {
+        if existing.contains(taken) {
+            anyhow::bail!("集合 {} 已存在, 请删除或重命名后再重新计算", taken);
+        }
+    }
+    // 新集合记录新的模型与维度
+    let mut metadata = store.collection_metadata(name).await?.unwrap_or_default();
+    metadata.insert("embedding_model".to_string(), Value::from(model));
+    metadata.insert("embedding_dim".to_string(), Value::from(client.dimension()));
+
+    let mut offset: usize = 0;
+    loop {
+        let mut records = store.get_records(name, offset, PAGE_SIZE).await?;
+        if records.is_empty() { break }
+
+        for batch in records.chunks_mut(batch_size) {
+            let texts = batch.iter().map(|r| r.document.as_str()).collect::<Vec<&str>>();
+            let embeddings = client.embed(&texts).await?;
+            if let Some(embedding) = embeddings.first().filter(|e| e.len() != client.dimension()) {
+                anyhow::bail!(
+                    "向量模型 {} 返回的维度为 {}, 与配置的 embedding_dim={} 不一致",
+                    model, embedding.len(), client.dimension()
+                );
+            }
+            for (record, embedding) in batch.iter_mut().zip(embeddings) {
+                record.embedding = embedding;
+            }
+        }
+        offset += records.len();
+        store.upsert_records(&tmp_name, records, Some(&metadata)).await?;
+        println!("已重新计算 {}/{}", offset, total);
+    }
+    store.open_target(&tmp_name, Some(&metadata)).await?;
+
+    // Chroma没有事务, 通过两次重命名完成替换, 失败时回滚
+    store.rename_collection(name, &old_name).await?;
+    if let Err(err) = store.rename_collection(&tmp_name, name).await {
+        store.rename_collection(&old_name, name).await?;
+        return Err(err);
+    }
+    if !keep_old {
+        store.delete_collection(&old_name).await?;
+    }
+
+    println!("集合 {} 已使用 {} 重新计算向量", name, model);
+    let key = match config.embedding_provider {
+        EmbeddingProvider::Zhipu => "zhipu_embedding_model",
+        _ => "embedding_model",
+    };
+    println!("请将配置中的 {} 修改为 {} 后再进行查询", key, model);
+    Ok(())
+}
@@ -3,6 +3,8 @@ use dotenv::dotenv;
 use handler::Cli;
 use serde::{Deserialize, Serialize};
 use db::create_pool;
+use embedding::EmbeddingProvider;
+use vector_store::{RerankMode, VectorBackend};
 
 mod handler;
 mod document;
@@ -25,16 +27,77 @@ async fn main() -> anyhow::Result<()> {
 pub struct Config {
     // Database
     db_url: String,
+    #[serde(default)]
+    vector_backend: VectorBackend,
+    // local 存储的目录, 为空时使用默认值
+    #[serde(default)]
+    local_store_dir: String,
 
     // Query
     n_results: usize,
+    #[serde(default)]
+    recency_weight: f32,
+    // 混合检索中向量与BM25关键词的RRF权重, keyword_weight 为0时只用向量检索, vector_weight 为0时视为1
+    #[serde(default)]
+    vector_weight: f32,
+    #[serde(default)]
+    keyword_weight: f32,
+    // 重排: none / cross_encoder / llm, 从 rerank_candidates 个候选中重排后返回 n_results 条
+    #[serde(default)]
+    reranker: RerankMode,
+    #[serde(default)]
+    rerank_candidates: usize,
+    // cross_encoder 的服务地址与模型, 如 http://localhost:8080/rerank
+    #[serde(default)]
+    rerank_url: String,
+    #[serde(default)]
+    rerank_model: String,
+    // MMR多样性: 0表示不启用, 取值(0, 1], 越小结果越分散
+    #[serde(default)]
+    mmr_lambda: f32,
+    // 相似度(1 - 距离)低于该值的结果不返回, 0表示不过滤
+    #[serde(default)]
+    score_threshold: f32,
 
     // Chunk
     chunk_size: u32,
+
+    // 分词: jieba格式的用户词典与停用词表(每行一个词)的路径
+    #[serde(default)]
+    user_dicts: Vec<String>,
+    #[serde(default)]
+    stop_words: Vec<String>,
     
     // Embedding
     embedding_dim: u32,
     batch: u32,
+    // 每千tokens的价格, 仅用于 dry-run 估算
+    #[serde(default)]
+    embedding_price: f64,
+
+    // 请求超时(秒)、429/5xx的重试次数与每分钟请求上限, 为空时使用默认值
+    #[serde(default)]
+    embedding_timeout: Option<u64>,
+    #[serde(default)]
+    embedding_max_retries: Option<u32>,
+    #[serde(default)]
+    embedding_rpm: Option<u32>,
+
+    // 向量缓存目录与容量(MB), 为空时使用默认值
+    #[serde(default)]
+    embedding_cache_dir: String,
+    #[serde(default)]
+    embedding_cache_mb: u64,
+
+    #[serde(default)]
+    embedding_provider: EmbeddingProvider,
+    // openai、ollama 与 local 使用以下配置, zhipu 使用 zhipu_* 配置
+    #[serde(default)]
+    embedding_url: String,
+    #[serde(default)]
+    embedding_model: String,
+    #[serde(default)]
+    embedding_api_key: String,
 
     zhipu_url: String,
     zhipu_embedding_model: String,
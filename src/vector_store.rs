@@ -1,16 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 use anyhow::Context;
-use chromadb::client::{ChromaAuthMethod, ChromaClient, ChromaClientOptions};
-use chromadb::collection::{ChromaCollection, CollectionEntries, GetOptions, QueryOptions, QueryResult};
+use chromadb::collection::{CollectionEntries, GetOptions, GetResult, QueryOptions, QueryResult};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, map::Map};
 
 use crate::chat::deepseek::ChatClient;
-use crate::embedding::zhipu::{EmbeddingClient, ZhipuOptions};
+use crate::db::create_pool;
+use crate::document::tokenizer::Tokenizer;
+use crate::embedding::{self, Embedder};
 use crate::Config;
 
+mod backend;
+mod bm25;
+mod chroma;
+mod filter;
+mod local;
+mod pgvector;
+mod rerank;
+
+pub use backend::{Backend, CollectionInfo};
+use bm25::Bm25Index;
+use rerank::Reranker;
+pub use rerank::RerankMode;
+
+/// 向量数据库的类型, 对应配置中的 `vector_backend`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorBackend {
+    #[default]
+    Chroma,
+    /// 保存在本地目录, 见 `local_store_dir`
+    Local,
+    /// 使用与问答记录相同的Postgres, 需要安装pgvector扩展
+    Pgvector,
+}
+
 pub struct VectorStore {
-    client: ChromaClient,
-    embedding_cli: EmbeddingClient,
+    client: Box<dyn Backend>,
+    embedding_cli: Box<dyn Embedder>,
     chat_cli: ChatClient,
 
     // Config when request
@@ -18,6 +47,159 @@ pub struct VectorStore {
 
     // Config when query
     n_results: usize,
+    recency_weight: f32,
+    vector_weight: f32,
+    keyword_weight: f32,
+
+    // 仅在开启关键词检索时加载, 索引按集合缓存, 通过本实例写入或记录数变化时重建
+    tokenizer: Option<Tokenizer>,
+    keyword_indexes: Mutex<HashMap<String, (usize, Arc<Bm25Index>)>>,
+
+    // 开启重排时先召回 rerank_candidates 条, 重排后保留 n_results 条
+    reranker: Option<Reranker>,
+    rerank_candidates: usize,
+
+    // 大于0时用MMR从候选中挑选 n_results 条, 越小越偏向多样性
+    mmr_lambda: f32,
+    // 丢弃相似度(1 - 距离)低于该值的结果
+    score_threshold: f32,
+}
+
+/// 集合中的一条完整记录, 用于备份与集合间的迁移
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    pub document: String,
+    pub metadata: Option<Map<String, Value>>,
+    pub embedding: Vec<f32>,
+}
+
+/// 跨集合检索的一条结果, `score` 为与问题向量的余弦相似度, 不同集合之间可直接比较;
+/// 混合检索与重排的得分只在同一集合内可比, 仅用于挑选各集合的候选
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub collection: String,
+    pub id: String,
+    pub document: String,
+    pub metadata: Option<Map<String, Value>>,
+    pub distance: f32,
+    pub score: f32,
+}
+
+pub struct Overview {
+    pub chunks: usize,
+    /// 集合较大时只读取了部分记录, 无法得知文档数
+    pub documents: Option<usize>,
+    pub topics: Vec<String>,
+}
+
+const OVERVIEW_TOPICS: usize = 5;
+const OVERVIEW_SAMPLE: usize = 200;
+// 集合元数据中记录向量模型与维度的键
+const EMBEDDING_KEYS: [&str; 2] = ["embedding_model", "embedding_dim"];
+// 混合检索时每一路召回 n_results 的倍数, 再融合截断
+const HYBRID_CANDIDATES: usize = 4;
+const RRF_K: f32 = 60.0;
+// 开启MMR且未开启重排时召回 n_results 的倍数作为候选
+const MMR_CANDIDATES: usize = 4;
+const SECONDS_PER_YEAR: f32 = 365.0 * 24.0 * 3600.0;
+
+// 按给定顺序重排查询结果中的一行, 不在 `order` 中的结果被丢弃
+fn reorder_row(result: &mut QueryResult, row: usize, order: &[usize]) {
+    fn permute<T: Clone>(items: &mut Vec<T>, order: &[usize]) {
+        *items = order.iter().map(|&i| items[i].clone()).collect();
+    }
+    permute(&mut result.ids[row], order);
+    if let Some(docs) = result.documents.as_mut() { permute(&mut docs[row], order) }
+    if let Some(metas) = result.metadatas.as_mut() { permute(&mut metas[row], order) }
+    if let Some(embeds) = result.embeddings.as_mut() { permute(&mut embeds[row], order) }
+    if let Some(dists) = result.distances.as_mut() { permute(&mut dists[row], order) }
+}
+
+// 每一行只保留前 `k` 个结果
+fn truncate_rows(result: &mut QueryResult, k: usize) {
+    result.ids.iter_mut().for_each(|row| row.truncate(k));
+    result.documents.iter_mut().flatten().for_each(|row| row.truncate(k));
+    result.metadatas.iter_mut().flatten().for_each(|row| row.truncate(k));
+    result.embeddings.iter_mut().flatten().for_each(|row| row.truncate(k));
+    result.distances.iter_mut().flatten().for_each(|row| row.truncate(k));
+}
+
+// 合并多个集合的结果: 按相似度排序, 内容相同的切块只保留相似度最高的一条, 保留前 `k` 条
+fn merge_hits(mut hits: Vec<Hit>, k: usize) -> Vec<Hit> {
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.distance.total_cmp(&b.distance)));
+    let mut seen = HashSet::new();
+    hits.retain(|hit| seen.insert(hit.document.trim().to_string()));
+    hits.truncate(k);
+    hits
+}
+
+// Used ONLY for LLM call, 解析可选的 `where` 参数
+fn parse_where(params: &HashMap<String, String>) -> anyhow::Result<Option<Value>> {
+    match params.get("where").map(|w| w.trim()).filter(|w| !w.is_empty()) {
+        Some(w) => Ok(Some(serde_json::from_str::<Value>(w)
+            .map_err(|err| anyhow::anyhow!("参数'where'必须是JSON对象, 如 {{\"source\": \"a.pdf\"}}: {}", err))?)),
+        None => Ok(None),
+    }
+}
+
+// 取出查询结果的第一行, 得分只在此处由距离换算, 之后统一读取 `score`
+fn first_row_hits(coll_name: &str, result: QueryResult) -> anyhow::Result<Vec<Hit>> {
+    let ids = result.ids.into_iter().next().unwrap_or_default();
+    let documents = result.documents.and_then(|d| d.into_iter().next()).unwrap_or_default();
+    let metadatas = result.metadatas.and_then(|m| m.into_iter().next()).unwrap_or_default();
+    let distances = result.distances.and_then(|d| d.into_iter().next()).unwrap_or_default();
+    if distances.len() < ids.len() {
+        anyhow::bail!("集合 {} 的查询结果缺少距离, 无法计算相似度", coll_name);
+    }
+    Ok(ids.into_iter().enumerate().map(|(i, id)| Hit {
+        collection: coll_name.to_string(),
+        id,
+        document: documents.get(i).cloned().unwrap_or_default(),
+        metadata: metadatas.get(i).cloned().flatten(),
+        distance: distances[i],
+        score: similarity(distances[i]),
+    }).collect())
+}
+
+// Used ONLY for LLM call, 一条检索结果的ID、来源、页码、相似度与内容
+#[derive(Serialize)]
+struct Evidence<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collection: Option<&'a str>,
+    id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<&'a Value>,
+    score: f32,
+    text: &'a str,
+}
+
+// Used ONLY for LLM call, 以JSON数组列出检索结果, `with_collection` 时标注所属集合
+fn describe_hits(hits: &[Hit], with_collection: bool) -> anyhow::Result<String> {
+    if hits.is_empty() {
+        return Ok("没有找到满足条件的内容，请尝试放宽过滤条件或更换关键词".to_string());
+    }
+    let evidences = hits.iter().map(|hit| {
+        let field = |key: &str| hit.metadata.as_ref().and_then(|m| m.get(key));
+        Evidence {
+            collection: with_collection.then_some(hit.collection.as_str()),
+            id: &hit.id,
+            source: field("source"),
+            page: field("page"),
+            score: (hit.score * 1000.0).round() / 1000.0,
+            text: &hit.document,
+        }
+    }).collect::<Vec<Evidence>>();
+    let note = "以下是API输出内容，按相关性从高到低排列，score为相似度，检查是否包含充足的信息以回答问题，\
+        如果不足，请尝试更换关键词继续查询，回答时请注明引用的来源：";
+    Ok(format!("{}\n{}", note, serde_json::to_string_pretty(&evidences)?))
+}
+
+/// 相似度取 1 - 距离, 限制在[0, 1]; 各后端返回的都是余弦距离, 融合或重排后的距离为 1 - 得分
+pub fn similarity(distance: f32) -> f32 {
+    (1.0 - distance).clamp(0.0, 1.0)
 }
 
 impl VectorStore {
@@ -41,42 +223,157 @@ impl VectorStore {
         dot_product / (norm_v1 * norm_v2)
     }
 
-    async fn get_all_ids(&self, coll_name: &str, offset: usize, limit: usize) -> anyhow::Result<Vec<String>> {
-        let collection = self.client.get_collection(coll_name).await?;
-        let options = GetOptions {
-            offset: Some(offset),
-            limit: Some(limit),
-            ..Default::default()
-        };
-        let ids = collection.get(options).await?.ids;
-        Ok(ids)
-    }
-
     pub async fn from_config(config: &Config) -> anyhow::Result<VectorStore>
     {
-        let auth = ChromaAuthMethod::None;
-        let client = ChromaClient::new(
-            ChromaClientOptions { url: Some(config.db_url.clone()), auth, ..Default::default() }
-        ).await.with_context(|| "Database Connection Failed")?;
-        let options = ZhipuOptions::new(
-            &config.zhipu_api_key, &config.zhipu_url, &config.zhipu_embedding_model
-        );
-        let embedding_cli = EmbeddingClient::new(options);
+        let client: Box<dyn Backend> = match config.vector_backend {
+            VectorBackend::Chroma => Box::new(chroma::ChromaBackend::connect(&config.db_url).await?),
+            VectorBackend::Local => {
+                let dir = match config.local_store_dir.as_str() {
+                    "" => local::DEFAULT_DIR,
+                    dir => dir,
+                };
+                Box::new(local::LocalBackend::open(Path::new(dir))?)
+            },
+            VectorBackend::Pgvector => Box::new(pgvector::PgvectorBackend::connect(create_pool().await?).await?),
+        };
+        let embedding_cli = embedding::from_config(config);
         let chat_cli = ChatClient::from_config(&config);
         let n_results = &config.n_results;
         let batch = config.batch;
-        Ok(VectorStore { client, embedding_cli, chat_cli, n_results: n_results.clone(), batch })
+        Ok(VectorStore {
+            client, embedding_cli, chat_cli, n_results: n_results.clone(), batch,
+            recency_weight: config.recency_weight,
+            vector_weight: if config.vector_weight > 0.0 { config.vector_weight } else { 1.0 },
+            keyword_weight: config.keyword_weight,
+            tokenizer: match config.keyword_weight > 0.0 {
+                true => Some(Tokenizer::from_config(config)?),
+                false => None,
+            },
+            keyword_indexes: Mutex::new(HashMap::new()),
+            reranker: Reranker::from_config(config),
+            rerank_candidates: match config.rerank_candidates {
+                0 => config.n_results * HYBRID_CANDIDATES,
+                n => n.max(config.n_results),
+            },
+            mmr_lambda: config.mmr_lambda.clamp(0.0, 1.0),
+            score_threshold: config.score_threshold,
+        })
     }
 
-    async fn get_collection(
-        &self, 
-        coll_name: &str, 
-        metadata: Option<Map<String, Value>>
-    ) -> anyhow::Result<ChromaCollection> {
-        self.client.get_or_create_collection(coll_name, metadata).await
+    // 只读取已存在的集合, 不存在时报错
+    async fn get_collection(&self, coll_name: &str) -> anyhow::Result<CollectionInfo> {
+        self.client.get_collection(coll_name).await
+    }
+
+    /// 用当前向量模型写入前调用: 集合不存在时创建并记录向量模型与维度,
+    /// 已存在时检查模型与维度(`dim` 为待写入向量的维度), 缺少这两个键的旧集合在此补写
+    pub async fn open_collection(
+        &self,
+        coll_name: &str,
+        metadata: Option<Map<String, Value>>,
+        dim: Option<usize>,
+    ) -> anyhow::Result<CollectionInfo> {
+        let model = self.embedding_cli.model();
+        match self.client.get_collection(coll_name).await {
+            Ok(collection) => {
+                self.check_embedding(&collection, Some(model), dim)?;
+                self.backfill_embedding(collection, Some(model), dim).await
+            },
+            Err(_) => {
+                let mut metadata = metadata.unwrap_or_default();
+                metadata.insert("embedding_model".to_string(), Value::from(model));
+                metadata.insert("embedding_dim".to_string(), Value::from(dim.unwrap_or(self.embedding_cli.dimension())));
+                self.client.create_collection(coll_name, metadata).await
+            },
+        }
+    }
+
+    // 旧版本创建的集合没有记录模型与维度, 无法检查, 首次写入时由 `backfill_embedding` 补写
+    fn check_embedding(
+        &self,
+        collection: &CollectionInfo,
+        model: Option<&str>,
+        dim: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let metadata = match collection.metadata() {
+            Some(metadata) => metadata,
+            None => return Ok(()),
+        };
+        let coll_model = metadata.get("embedding_model").and_then(|m| m.as_str());
+        if let (Some(model), Some(coll_model)) = (model, coll_model) {
+            if model != coll_model {
+                anyhow::bail!(
+                    "集合 {} 使用向量模型 {} 构建, 与当前的 {} 不一致, 请修改配置或使用 doc reembed 重新计算",
+                    collection.name(), coll_model, model
+                );
+            }
+        }
+        let coll_dim = metadata.get("embedding_dim").and_then(|d| d.as_u64());
+        if let (Some(dim), Some(coll_dim)) = (dim, coll_dim) {
+            if dim as u64 != coll_dim {
+                anyhow::bail!(
+                    "集合 {} 的向量维度为 {}, 与当前的 {} 不一致",
+                    collection.name(), coll_dim, dim
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // 为缺少向量模型或维度的集合补写写入时使用的值, 返回更新后的集合信息
+    async fn backfill_embedding(
+        &self,
+        collection: CollectionInfo,
+        model: Option<&str>,
+        dim: Option<usize>,
+    ) -> anyhow::Result<CollectionInfo> {
+        let mut metadata = collection.metadata().cloned().unwrap_or_default();
+        let missing = [("embedding_model", model.map(Value::from)), ("embedding_dim", dim.map(Value::from))]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .filter(|(key, _)| !metadata.contains_key(key))
+            .collect::<Vec<(String, Value)>>();
+        if missing.is_empty() {
+            return Ok(collection);
+        }
+        metadata.extend(missing);
+        self.client.modify_collection(collection.name(), None, Some(&metadata)).await?;
+        Ok(CollectionInfo::new(collection.name(), Some(metadata)))
+    }
+
+    // get_or_create 不会修改已存在集合的元数据, 需要手动合并;
+    // 向量模型与维度只在创建时写入, 与已有的值不同时报错
+    async fn merge_collection_metadata(
+        &self,
+        collection: &CollectionInfo,
+        mut metadata: Map<String, Value>,
+    ) -> anyhow::Result<()> {
+        for key in EMBEDDING_KEYS {
+            let (Some(value), Some(existing)) = (metadata.remove(key), collection.metadata().and_then(|m| m.get(key))) else {
+                continue;
+            };
+            if &value != existing {
+                anyhow::bail!("集合 {} 的 {} 为 {}, 不能修改为 {}", collection.name(), key, existing, value);
+            }
+        }
+        let mut merged = collection.metadata().cloned().unwrap_or_default();
+        if metadata.iter().all(|(k, v)| merged.get(k) == Some(v)) {
+            return Ok(());
+        }
+        merged.extend(metadata);
+        self.client.modify_collection(collection.name(), None, Some(&merged)).await
+    }
+
+    pub async fn rename_collection(&self, coll_name: &str, new_name: &str) -> anyhow::Result<()> {
+        if self.list_collections().await?.iter().any(|coll| coll.name() == new_name) {
+            anyhow::bail!("Collection {} already exists", new_name);
+        }
+        self.invalidate_keyword_index(coll_name);
+        self.invalidate_keyword_index(new_name);
+        self.client.modify_collection(coll_name, Some(new_name), None).await
     }
 
-    pub async fn list_collections(&self) -> anyhow::Result<Vec<ChromaCollection>> {
+    pub async fn list_collections(&self) -> anyhow::Result<Vec<CollectionInfo>> {
         self.client.list_collections().await
     }
 
@@ -84,6 +381,7 @@ impl VectorStore {
         &self,
         coll_name: &str,
     ) -> anyhow::Result<()> {
+        self.invalidate_keyword_index(coll_name);
         self.client.delete_collection(coll_name).await
             .with_context(|| format!("Cannot remove {}", coll_name))
     }
@@ -93,72 +391,552 @@ impl VectorStore {
         coll_name: &str,
         ids: Vec<&str>, 
         documents: Vec<&str>,
+        metadatas: Option<Vec<Map<String, Value>>>,
         coll_metadata: Option<Map<String, Value>>,
     ) -> anyhow::Result<()> {
-        let batch_size = self.batch as usize;
+        // 在调用向量模型之前检查, 避免浪费额度
+        self.open_collection(coll_name, coll_metadata.clone(), Some(self.embedding_cli.dimension())).await?;
+
+        let embeddings = self.embed(&documents).await?;
+        self.upsert(coll_name, ids, documents, embeddings, metadatas, coll_metadata).await
+    }
+
+    /// 按 batch 分批请求向量模型, 批次不超过服务支持的输入数
+    pub async fn embed(&self, documents: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let batch_size = (self.batch as usize).min(self.embedding_cli.max_input());
         let mut all_embeddings = Vec::new();
 
         for chunk in documents.chunks(batch_size) {
-            let chunk_vec = chunk.to_vec();
-            let embeddings = self.embedding_cli.zhipu_embedding(&chunk_vec).await?
-                .iter().map(|embed| embed.embedding.clone()).collect::<Vec<Vec<f32>>>();
-            all_embeddings.extend(embeddings);
+            all_embeddings.extend(self.embedding_cli.embed(chunk).await?);
+        }
+        if let Some(embedding) = all_embeddings.first() {
+            if embedding.len() != self.embedding_cli.dimension() {
+                anyhow::bail!(
+                    "向量模型 {} 返回的维度为 {}, 与配置的 embedding_dim={} 不一致",
+                    self.embedding_cli.model(), embedding.len(), self.embedding_cli.dimension()
+                );
+            }
         }
+        Ok(all_embeddings)
+    }
 
+    /// 写入由当前向量模型计算好向量的记录
+    pub async fn upsert(
+        &self,
+        coll_name: &str,
+        ids: Vec<&str>,
+        documents: Vec<&str>,
+        embeddings: Vec<Vec<f32>>,
+        metadatas: Option<Vec<Map<String, Value>>>,
+        coll_metadata: Option<Map<String, Value>>,
+    ) -> anyhow::Result<()> {
         let entries = CollectionEntries {
             ids,
-            metadatas: None,
+            metadatas,
             documents: Some(documents),
-            embeddings: Some(all_embeddings),
+            embeddings: Some(embeddings),
         };
-        let collection = self.get_collection(coll_name, coll_metadata).await?;
-        collection.upsert(entries, None).await?;
+        let coll_metadata = coll_metadata.filter(|metadata| !metadata.is_empty());
+        let dim = entries.embeddings.as_ref().and_then(|e| e.first()).map(|e| e.len());
+        let collection = self.open_collection(coll_name, coll_metadata.clone(), dim).await?;
+        if let Some(coll_metadata) = coll_metadata {
+            self.merge_collection_metadata(&collection, coll_metadata).await?;
+        }
+        // 同一ID的内容可能被替换, 记录数不变也要重建索引
+        self.invalidate_keyword_index(coll_name);
+        self.client.upsert(coll_name, entries).await
+    }
+
+    /// 分页读取集合中的记录, `include` 可包含 documents, metadatas, embeddings
+    pub async fn get_page(
+        &self,
+        coll_name: &str,
+        offset: usize,
+        limit: usize,
+        include: &[&str],
+    ) -> anyhow::Result<GetResult> {
+        self.get_collection(coll_name).await?;
+        let options = GetOptions {
+            offset: Some(offset),
+            limit: Some(limit),
+            include: Some(include.iter().map(|s| s.to_string()).collect()),
+            ..Default::default()
+        };
+        self.client.get(coll_name, options).await
+    }
+
+    /// 分页读取包含向量在内的完整记录
+    pub async fn get_records(&self, coll_name: &str, offset: usize, limit: usize) -> anyhow::Result<Vec<Record>> {
+        let page = self.get_page(coll_name, offset, limit, &["documents", "metadatas", "embeddings"]).await?;
+        let count = page.ids.len();
+        let documents = page.documents.unwrap_or_else(|| vec![None; count]);
+        let metadatas = page.metadatas.unwrap_or_else(|| vec![None; count]);
+        let embeddings = page.embeddings.unwrap_or_else(|| vec![None; count]);
+
+        page.ids.into_iter()
+            .zip(documents)
+            .zip(metadatas.into_iter().zip(embeddings))
+            .map(|((id, document), (metadata, embedding))| {
+                let embedding = embedding
+                    .ok_or_else(|| anyhow::anyhow!("Record {} has no embedding", id))?;
+                Ok(Record { id, document: document.unwrap_or_default(), metadata, embedding })
+            })
+            .collect()
+    }
+
+    /// 写入来自其他集合或备份的记录前调用: 目标不存在时以来源的元数据创建,
+    /// 已存在时只检查向量模型与维度是否与来源一致, 并为旧集合补写这两个键
+    pub async fn open_target(
+        &self,
+        coll_name: &str,
+        source: Option<&Map<String, Value>>,
+    ) -> anyhow::Result<CollectionInfo> {
+        match self.client.get_collection(coll_name).await {
+            Ok(collection) => {
+                let model = source.and_then(|m| m.get("embedding_model")).and_then(|m| m.as_str());
+                let dim = source.and_then(|m| m.get("embedding_dim")).and_then(|d| d.as_u64());
+                self.check_embedding(&collection, model, dim.map(|d| d as usize))?;
+                self.backfill_embedding(collection, model, dim.map(|d| d as usize)).await
+            },
+            Err(_) => self.client.create_collection(coll_name, source.cloned().unwrap_or_default()).await,
+        }
+    }
+
+    /// 写入完整记录, 无需重新计算向量, `source` 为来源集合或备份的元数据, 见 `open_target`
+    pub async fn upsert_records(
+        &self,
+        coll_name: &str,
+        records: Vec<Record>,
+        source: Option<&Map<String, Value>>,
+    ) -> anyhow::Result<()> {
+        let collection = self.open_target(coll_name, source).await?;
+        let dim = records.first().map(|r| r.embedding.len());
+        self.check_embedding(&collection, None, dim)?;
+        self.backfill_embedding(collection, None, dim).await?;
+
+        // Chroma要求同一批记录要么都有元数据, 要么都没有
+        let (with_meta, without_meta): (Vec<Record>, Vec<Record>) = records.into_iter()
+            .partition(|record| record.metadata.is_some());
+
+        for (records, has_meta) in [(with_meta, true), (without_meta, false)] {
+            if records.is_empty() { continue }
+            let entries = CollectionEntries {
+                ids: records.iter().map(|r| r.id.as_str()).collect(),
+                metadatas: has_meta.then(|| records.iter().map(|r| r.metadata.clone().unwrap_or_default()).collect()),
+                documents: Some(records.iter().map(|r| r.document.as_str()).collect()),
+                embeddings: Some(records.iter().map(|r| r.embedding.clone()).collect()),
+            };
+            self.invalidate_keyword_index(coll_name);
+            self.client.upsert(coll_name, entries).await?;
+        }
         Ok(())
     }
 
+    pub async fn set_description(&self, coll_name: &str, description: &str) -> anyhow::Result<()> {
+        let collection = self.client.get_collection(coll_name).await
+            .with_context(|| format!("Cannot find {}", coll_name))?;
+        let mut metadata = Map::new();
+        metadata.insert("description".to_string(), Value::from(description));
+        self.merge_collection_metadata(&collection, metadata).await
+    }
+
+    /// 统计集合的切块数, 并从前 OVERVIEW_SAMPLE 条记录中取文档标题(或文件名)作为主题示例,
+    /// 只有样本覆盖整个集合时才给出文档数, 避免每次调用都读取全部记录
+    pub async fn overview(&self, coll_name: &str) -> anyhow::Result<Overview> {
+        let chunks = self.count(coll_name).await?;
+        let page = self.get_page(coll_name, 0, OVERVIEW_SAMPLE, &["metadatas"]).await?;
+
+        let mut sources = HashSet::new();
+        let mut topics = Vec::new();
+        for metadata in page.metadatas.iter().flatten().flatten() {
+            let source = match metadata.get("source").and_then(|s| s.as_str()) {
+                Some(source) => source,
+                None => continue,
+            };
+            if !sources.insert(source.to_string()) || topics.len() >= OVERVIEW_TOPICS {
+                continue;
+            }
+            let topic = metadata.get("title").and_then(|t| t.as_str())
+                .or_else(|| std::path::Path::new(source).file_stem().and_then(|s| s.to_str()));
+            if let Some(topic) = topic {
+                topics.push(topic.to_string());
+            }
+        }
+        let documents = (page.ids.len() >= chunks).then_some(sources.len());
+        Ok(Overview { chunks, documents, topics })
+    }
+
+    pub async fn count(&self, coll_name: &str) -> anyhow::Result<usize> {
+        self.client.count(coll_name).await
+    }
+
+    pub async fn collection_metadata(&self, coll_name: &str) -> anyhow::Result<Option<Map<String, Value>>> {
+        let collection = self.client.get_collection(coll_name).await?;
+        Ok(collection.metadata().cloned())
+    }
+
+    pub async fn get_by_ids(
+        &self,
+        coll_name: &str,
+        ids: Vec<String>,
+        include: &[&str],
+    ) -> anyhow::Result<GetResult> {
+        self.get_collection(coll_name).await?;
+        let options = GetOptions {
+            ids,
+            include: Some(include.iter().map(|s| s.to_string()).collect()),
+            ..Default::default()
+        };
+        self.client.get(coll_name, options).await
+    }
+
+    /// 按元数据过滤读取记录, 如 `{"source": "a.pdf"}`
+    pub async fn get_where(
+        &self,
+        coll_name: &str,
+        where_metadata: Value,
+        include: &[&str],
+    ) -> anyhow::Result<GetResult> {
+        self.get_collection(coll_name).await?;
+        let options = GetOptions {
+            where_metadata: Some(where_metadata),
+            include: Some(include.iter().map(|s| s.to_string()).collect()),
+            ..Default::default()
+        };
+        self.client.get(coll_name, options).await
+    }
+
+    /// 判断每个向量是否与集合中已有的记录(ID不同)或排在它之前的向量过于相似,
+    /// `threshold` 为余弦相似度
+    pub async fn near_duplicates(
+        &self,
+        coll_name: &str,
+        ids: &[&str],
+        embeddings: &[Vec<f32>],
+        threshold: f32,
+    ) -> anyhow::Result<Vec<bool>> {
+        let collection = self.get_collection(coll_name).await?;
+        self.check_embedding(&collection, Some(self.embedding_cli.model()), embeddings.first().map(|e| e.len()))?;
+        let mut nearest: Vec<Option<Vec<f32>>> = vec![None; embeddings.len()];
+
+        if self.client.count(coll_name).await? > 0 {
+            let batch_size = self.batch as usize;
+            for (n, chunk) in embeddings.chunks(batch_size).enumerate() {
+                // 取两个近邻, 以跳过同一ID的旧版本
+                let query = QueryOptions {
+                    query_embeddings: Some(chunk.to_vec()),
+                    n_results: Some(2),
+                    include: Some(vec!["embeddings"]),
+                    ..Default::default()
+                };
+                let result = self.client.query(coll_name, query).await?;
+                let rows = match result.embeddings {
+                    Some(rows) => rows,
+                    None => continue,
+                };
+                for (i, (row_ids, row)) in result.ids.into_iter().zip(rows).enumerate() {
+                    let index = n * batch_size + i;
+                    nearest[index] = row_ids.iter().zip(row)
+                        .find(|(id, _)| id.as_str() != ids[index])
+                        .map(|(_, embed)| embed);
+                }
+            }
+        }
+
+        let mut accepted: Vec<&Vec<f32>> = Vec::new();
+        let mut duplicates = Vec::with_capacity(embeddings.len());
+        for (embedding, near) in embeddings.iter().zip(nearest) {
+            let is_duplicate = near.is_some_and(|near| self.dissimilarity(embedding, &near) >= threshold)
+                || accepted.iter().any(|other| self.dissimilarity(embedding, other) >= threshold);
+            if !is_duplicate {
+                accepted.push(embedding);
+            }
+            duplicates.push(is_duplicate);
+        }
+        Ok(duplicates)
+    }
+
+    /// 检索与 `query_text` 相关的切块, `where_metadata` 按Chroma的 `where` 语法过滤, 如
+    /// `{"department": "finance", "year": {"$gte": 2023}}`
     pub async fn query_text(
         &self,
         coll_name: &str,
         query_text: Vec<&str>,
+        where_metadata: Option<Value>,
     ) -> anyhow::Result<QueryResult> {
-        let collection = self.get_collection(coll_name, None).await?;
-        let embeddings = self.embedding_cli.zhipu_embedding(&query_text).await?
-            .iter().map(|embed| embed.embedding.clone()).collect::<Vec<Vec<f32>>>();
+        let collection = self.get_collection(coll_name).await?;
+        self.check_embedding(&collection, Some(self.embedding_cli.model()), Some(self.embedding_cli.dimension()))?;
+        let embeddings = self.embedding_cli.embed(&query_text).await?;
+        self.search(coll_name, &query_text, embeddings, where_metadata).await
+    }
+
+    // 用已计算好的问题向量检索, 调用方负责检查集合的向量模型
+    async fn search(
+        &self,
+        coll_name: &str,
+        query_text: &[&str],
+        embeddings: Vec<Vec<f32>>,
+        where_metadata: Option<Value>,
+    ) -> anyhow::Result<QueryResult> {
+        let candidates = match (&self.reranker, self.mmr_lambda > 0.0) {
+            (Some(_), _) => self.rerank_candidates,
+            (None, true) => self.n_results * MMR_CANDIDATES,
+            (None, false) => self.n_results,
+        };
+        let n_results = match self.tokenizer {
+            Some(_) => candidates * HYBRID_CANDIDATES,
+            None => candidates,
+        };
         let query = QueryOptions {
             query_texts: None,
             query_embeddings: Some(embeddings),
-            n_results: Some(self.n_results),
+            n_results: Some(n_results),
+            where_metadata: where_metadata.clone(),
+            include: (self.mmr_lambda > 0.0).then(|| vec!["documents", "metadatas", "distances", "embeddings"]),
             ..Default::default()
         };
-        let query_result = collection.query(query, None).await?;
+        let mut query_result = self.client.query(coll_name, query).await?;
+        if self.recency_weight > 0.0 {
+            self.rank_by_recency(&mut query_result);
+        }
+        if let Some(tokenizer) = &self.tokenizer {
+            query_result = self.fuse_keyword(coll_name, query_text, query_result, tokenizer, candidates, where_metadata.as_ref()).await?;
+        }
+        if let Some(reranker) = &self.reranker {
+            self.rerank(reranker, query_text, &mut query_result).await?;
+        }
+        if self.mmr_lambda > 0.0 {
+            self.diversify(coll_name, &mut query_result).await?;
+        }
+        truncate_rows(&mut query_result, self.n_results);
+        if self.score_threshold > 0.0 {
+            if let Some(distances) = query_result.distances.clone() {
+                for (row, distances) in distances.iter().enumerate() {
+                    let kept = (0..distances.len())
+                        .filter(|&i| similarity(distances[i]) >= self.score_threshold)
+                        .collect::<Vec<usize>>();
+                    reorder_row(&mut query_result, row, &kept);
+                }
+            }
+        }
         Ok(query_result)
     }
 
+    // 按重排得分排序, 距离改为 1 - 得分
+    async fn rerank(&self, reranker: &Reranker, query_text: &[&str], result: &mut QueryResult) -> anyhow::Result<()> {
+        let documents = match &result.documents {
+            Some(documents) => documents.clone(),
+            None => return Ok(()),
+        };
+        for (row, (text, docs)) in query_text.iter().zip(documents).enumerate() {
+            let scores = reranker.score(&self.chat_cli, text, &docs).await?;
+            let mut order = (0..scores.len()).collect::<Vec<usize>>();
+            order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+            if let Some(distances) = result.distances.as_mut() {
+                distances[row] = scores.iter().map(|score| 1.0 - score).collect();
+            }
+            reorder_row(result, row, &order);
+        }
+        Ok(())
+    }
+
+    // MMR: 依次选出 lambda * 相关性 - (1 - lambda) * 与已选结果的最大相似度 最高的候选,
+    // 相关性取 1 - 距离, 选中的 n_results 条排在前面
+    async fn diversify(&self, coll_name: &str, result: &mut QueryResult) -> anyhow::Result<()> {
+        let embeddings = match &result.embeddings {
+            Some(embeddings) => embeddings.clone(),
+            None => {
+                // 融合关键词后的结果不带向量, 需要补充
+                let ids = result.ids.iter().flatten().cloned().collect::<HashSet<String>>();
+                let fetched = self.get_by_ids(coll_name, ids.into_iter().collect(), &["embeddings"]).await?;
+                let vectors = fetched.ids.into_iter()
+                    .zip(fetched.embeddings.unwrap_or_default())
+                    .filter_map(|(id, embedding)| Some((id, embedding?)))
+                    .collect::<HashMap<String, Vec<f32>>>();
+                result.ids.iter().map(|row| row.iter().map(|id| {
+                    vectors.get(id).cloned().ok_or_else(|| anyhow::anyhow!("无法读取 {} 的向量", id))
+                }).collect()).collect::<anyhow::Result<Vec<Vec<Vec<f32>>>>>()?
+            },
+        };
+
+        for (row, embeds) in embeddings.iter().enumerate() {
+            let relevance = match &result.distances {
+                Some(distances) => distances[row].iter().map(|d| 1.0 - d).collect::<Vec<f32>>(),
+                None => (0..embeds.len()).map(|i| 1.0 - i as f32 / embeds.len() as f32).collect(),
+            };
+            let mut selected: Vec<usize> = Vec::with_capacity(self.n_results);
+            let mut remaining = (0..embeds.len()).collect::<Vec<usize>>();
+            while selected.len() < self.n_results && !remaining.is_empty() {
+                let scores = remaining.iter().map(|&i| {
+                    let redundancy = selected.iter()
+                        .map(|&j| self.dissimilarity(&embeds[i], &embeds[j]))
+                        .reduce(f32::max)
+                        .unwrap_or(0.0);
+                    self.mmr_lambda * relevance[i] - (1.0 - self.mmr_lambda) * redundancy
+                }).collect::<Vec<f32>>();
+                let best = (0..scores.len()).max_by(|&a, &b| scores[a].total_cmp(&scores[b])).unwrap_or(0);
+                selected.push(remaining.remove(best));
+            }
+            selected.extend(remaining);
+            reorder_row(result, row, &selected);
+        }
+        Ok(())
+    }
+
+    fn invalidate_keyword_index(&self, coll_name: &str) {
+        self.keyword_indexes.lock().unwrap().remove(coll_name);
+    }
+
+    async fn keyword_index(&self, coll_name: &str, tokenizer: &Tokenizer) -> anyhow::Result<Arc<Bm25Index>> {
+        let count = self.count(coll_name).await?;
+        if let Some((indexed, index)) = self.keyword_indexes.lock().unwrap().get(coll_name) {
+            if *indexed == count {
+                return Ok(index.clone());
+            }
+        }
+
+        let mut documents: Vec<(String, String)> = Vec::with_capacity(count);
+        let mut offset: usize = 0;
+        let limit: usize = 1000;
+        loop {
+            let page = self.get_page(coll_name, offset, limit, &["documents"]).await?;
+            if page.ids.is_empty() { break }
+            offset += page.ids.len();
+            let docs = page.documents.unwrap_or_default();
+            documents.extend(page.ids.into_iter().zip(docs).map(|(id, doc)| (id, doc.unwrap_or_default())));
+        }
+        let index = Arc::new(Bm25Index::build(
+            documents.iter().map(|(id, doc)| (id.as_str(), doc.as_str())), tokenizer
+        ));
+        self.keyword_indexes.lock().unwrap().insert(coll_name.to_string(), (count, index.clone()));
+        Ok(index)
+    }
+
+    // 用RRF融合向量与BM25的排名, 距离改为 1 - 融合得分/满分
+    async fn fuse_keyword(
+        &self,
+        coll_name: &str,
+        query_text: &[&str],
+        result: QueryResult,
+        tokenizer: &Tokenizer,
+        limit: usize,
+        where_metadata: Option<&Value>,
+    ) -> anyhow::Result<QueryResult> {
+        let index = self.keyword_index(coll_name, tokenizer).await?;
+        let n_candidates = limit * HYBRID_CANDIDATES;
+        let best = (self.vector_weight + self.keyword_weight) / (RRF_K + 1.0);
+
+        let mut known: HashMap<String, (String, Option<Map<String, Value>>)> = HashMap::new();
+        let documents = result.documents.unwrap_or_default();
+        let metadatas = result.metadatas.unwrap_or_default();
+        for (row, ids) in result.ids.iter().enumerate() {
+            for (i, id) in ids.iter().enumerate() {
+                let document = documents.get(row).and_then(|d| d.get(i)).cloned().unwrap_or_default();
+                let metadata = metadatas.get(row).and_then(|m| m.get(i)).cloned().flatten();
+                known.insert(id.clone(), (document, metadata));
+            }
+        }
+
+        let mut rows = Vec::with_capacity(query_text.len());
+        for (row, text) in query_text.iter().enumerate() {
+            let mut keyword = index.search(text, tokenizer, n_candidates)
+                .into_iter().map(|(id, _)| id).collect::<Vec<String>>();
+            // BM25索引不含元数据, 由存储筛出满足过滤条件的关键词结果
+            if let Some(filter) = where_metadata.filter(|_| !keyword.is_empty()) {
+                let options = GetOptions {
+                    ids: keyword.clone(),
+                    where_metadata: Some(filter.clone()),
+                    include: Some(vec!["documents".to_string(), "metadatas".to_string()]),
+                    ..Default::default()
+                };
+                let fetched = self.client.get(coll_name, options).await?;
+                let documents = fetched.documents.unwrap_or_default();
+                let metadatas = fetched.metadatas.unwrap_or_default();
+                for (i, id) in fetched.ids.iter().enumerate() {
+                    let document = documents.get(i).cloned().flatten().unwrap_or_default();
+                    let metadata = metadatas.get(i).cloned().flatten();
+                    known.insert(id.clone(), (document, metadata));
+                }
+                let matched = fetched.ids.into_iter().collect::<HashSet<String>>();
+                keyword.retain(|id| matched.contains(id));
+            }
+            let vector = result.ids.get(row).cloned().unwrap_or_default();
+            let mut fused = bm25::fuse(&[(&vector, self.vector_weight), (&keyword, self.keyword_weight)], RRF_K);
+            fused.truncate(limit);
+            rows.push(fused);
+        }
+
+        // 只被关键词召回的切块需要补充内容与元数据
+        let missing = rows.iter().flatten()
+            .map(|(id, _)| id.clone())
+            .filter(|id| !known.contains_key(id))
+            .collect::<HashSet<String>>();
+        if !missing.is_empty() {
+            let fetched = self.get_by_ids(coll_name, missing.into_iter().collect(), &["documents", "metadatas"]).await?;
+            let documents = fetched.documents.unwrap_or_default();
+            let metadatas = fetched.metadatas.unwrap_or_default();
+            for (i, id) in fetched.ids.into_iter().enumerate() {
+                let document = documents.get(i).cloned().flatten().unwrap_or_default();
+                let metadata = metadatas.get(i).cloned().flatten();
+                known.insert(id, (document, metadata));
+            }
+        }
+
+        Ok(QueryResult {
+            ids: rows.iter().map(|row| row.iter().map(|(id, _)| id.clone()).collect()).collect(),
+            documents: Some(rows.iter().map(|row| row.iter().map(|(id, _)| known[id].0.clone()).collect()).collect()),
+            metadatas: Some(rows.iter().map(|row| row.iter().map(|(id, _)| known[id].1.clone()).collect()).collect()),
+            embeddings: None,
+            distances: Some(rows.iter().map(|row| row.iter().map(|(_, score)| 1.0 - score / best).collect()).collect()),
+        })
+    }
+
+    // 距离减去 recency_weight * 0.5^(距今年数), 较新的文档排在前面
+    fn rank_by_recency(&self, result: &mut QueryResult) {
+        let (metadatas, distances) = match (&result.metadatas, &result.distances) {
+            (Some(metadatas), Some(distances)) => (metadatas, distances),
+            _ => return,
+        };
+        let now = chrono::Utc::now().timestamp();
+        let orders = metadatas.iter().zip(distances).map(|(metas, dists)| {
+            let scores = metas.iter().zip(dists).map(|(meta, dist)| {
+                let recency = meta.as_ref()
+                    .and_then(|m| m.get("timestamp"))
+                    .and_then(|t| t.as_i64())
+                    .map(|ts| 0.5f32.powf((now - ts).max(0) as f32 / SECONDS_PER_YEAR))
+                    .unwrap_or(0.0);
+                dist - self.recency_weight * recency
+            }).collect::<Vec<f32>>();
+            let mut order = (0..scores.len()).collect::<Vec<usize>>();
+            order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+            order
+        }).collect::<Vec<Vec<usize>>>();
+
+        for (row, order) in orders.iter().enumerate() {
+            reorder_row(result, row, order);
+        }
+    }
+
     pub async fn clean(&self) -> anyhow::Result<()> {
         let collections = self.list_collections().await?;
         let mut iter = collections.iter();
         while let Some(item) = iter.next() {
             self.client.delete_collection(item.name()).await?;
         }
+        self.keyword_indexes.lock().unwrap().clear();
         Ok(())
     }
 
     pub async fn all_to_differ(&self, coll_name: &str, threshold: f32) -> anyhow::Result<Vec<String>> {
-        let collection = self.get_collection(coll_name, None).await?;
+        self.get_collection(coll_name).await?;
         let mut rnt_contexts: HashMap<String, Vec<f32>> = HashMap::new();
 
-        // 分页获取所有的文档ID
+        // 分页获取所有的文档与向量
         let mut offset: usize = 0;
         let limit: usize = 1000;
         loop {
-            let ids = self.get_all_ids(coll_name, offset, limit).await?;
-            if ids.is_empty() { break }
-            let get_options = GetOptions {
-                ids,
-                include: Some(vec!["documents".to_string(), "embeddings".to_string()]),
-                ..Default::default()
-            };
-            let result = collection.get(get_options).await?;
+            let result = self.get_page(coll_name, offset, limit, &["documents", "embeddings"]).await?;
+            if result.ids.is_empty() { break }
 
             let texts = match result.documents {
                 Some(docs) => docs,
@@ -197,27 +975,57 @@ impl VectorStore {
         Ok(rnt_contexts.keys().cloned().collect())
     }
 
+    /// 用已有的向量检索, 每个向量返回 `n_results` 个近邻
     pub async fn query_embedding(
         &self, 
         coll_name: &str,
         query_embeddings: Vec<Vec<f32>>, 
+        n_results: usize,
     ) -> anyhow::Result<QueryResult> {
+        let collection = self.get_collection(coll_name).await?;
+        self.check_embedding(&collection, None, query_embeddings.first().map(|e| e.len()))?;
         let query = QueryOptions {
             query_texts: None,
-            query_embeddings: None,
-            n_results: None,
+            query_embeddings: Some(query_embeddings),
+            n_results: Some(n_results),
             ..Default::default()
         };
-        let collection = self.get_collection(coll_name, None).await?;
-        let query_result = collection.query(query, None).await?;
+        let query_result = self.client.query(coll_name, query).await?;
         Ok(query_result)
-    }    
+    }
+
+    /// 用当前的向量模型计算可与集合比较的向量, 模型或维度与集合不一致时报错
+    pub async fn embed_for(&self, coll_name: &str, documents: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let collection = self.get_collection(coll_name).await?;
+        self.check_embedding(&collection, Some(self.embedding_cli.model()), Some(self.embedding_cli.dimension()))?;
+        self.embed(documents).await
+    }
 
     // Used ONLY for LLM call
     pub async fn list_collections_llm(&self) -> anyhow::Result<String> {
-        let colls = self.list_collections().await?
-            .iter().map(|coll| format!("\nname: {}, metadata: {:#?}\n", coll.name(), coll.metadata()))
-            .collect::<String>();
+        let mut colls = String::new();
+        for coll in self.list_collections().await? {
+            let overview = self.overview(coll.name()).await?;
+            let metadata = coll.metadata().cloned().unwrap_or_default();
+            let description = metadata.get("description").and_then(|d| d.as_str()).unwrap_or("无");
+            let others = metadata.iter()
+                .filter(|(k, _)| k.as_str() != "description")
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<String>>();
+
+            colls.push_str(&format!("\nname: {}\n", coll.name()));
+            colls.push_str(&format!("description: {}\n", description));
+            match overview.documents {
+                Some(documents) => colls.push_str(&format!("documents: {}, chunks: {}\n", documents, overview.chunks)),
+                None => colls.push_str(&format!("chunks: {}\n", overview.chunks)),
+            }
+            if !overview.topics.is_empty() {
+                colls.push_str(&format!("topics: {}\n", overview.topics.join(", ")));
+            }
+            if !others.is_empty() {
+                colls.push_str(&format!("metadata: {}\n", others.join(", ")));
+            }
+        }
         Ok(colls)
     }
 
@@ -232,16 +1040,92 @@ impl VectorStore {
             None => return Err(anyhow::anyhow!("参数'text'是必要的，表示你需要查找的文本的内容")),
         };
 
+        let where_metadata = parse_where(&params)?;
+
         let result = self.query_text(
-            coll_name.as_str(), vec! [text]
+            coll_name.as_str(), vec! [text], where_metadata
         ).await?;
+        describe_hits(&first_row_hits(coll_name, result)?, false)
+    }
 
-        if let Some(docs) = result.documents {
-            let note = "以下是API输出内容，检查是否包含充足的信息以回答问题，如果不足，请尝试更换关键词继续查询：";
-            Ok(docs[0][0].clone() + note)
-        } else {
-            Ok("None".to_string())
+    // 在一个集合中检索, 混合检索或重排后的得分在集合之间不可比, 改用与问题向量的余弦相似度
+    async fn query_collection(
+        &self,
+        coll_name: &str,
+        text: &str,
+        embedding: &[f32],
+        where_metadata: Option<Value>,
+    ) -> anyhow::Result<Vec<Hit>> {
+        let collection = self.get_collection(coll_name).await?;
+        self.check_embedding(&collection, Some(self.embedding_cli.model()), Some(embedding.len()))?;
+        let result = self.search(coll_name, &[text], vec![embedding.to_vec()], where_metadata).await?;
+        let mut hits = first_row_hits(coll_name, result)?;
+        if (self.tokenizer.is_none() && self.reranker.is_none()) || hits.is_empty() {
+            return Ok(hits);
         }
+
+        let ids = hits.iter().map(|hit| hit.id.clone()).collect::<Vec<String>>();
+        let fetched = self.get_by_ids(coll_name, ids, &["embeddings"]).await?;
+        let vectors = fetched.ids.into_iter()
+            .zip(fetched.embeddings.unwrap_or_default())
+            .filter_map(|(id, embedding)| Some((id, embedding?)))
+            .collect::<HashMap<String, Vec<f32>>>();
+        for hit in hits.iter_mut() {
+            let vector = vectors.get(&hit.id).ok_or_else(|| anyhow::anyhow!("无法读取 {} 的向量", hit.id))?;
+            hit.distance = local::cosine_distance(embedding, vector);
+            hit.score = similarity(hit.distance);
+        }
+        Ok(hits)
+    }
+
+    /// 并发检索多个集合(为空时检索全部), 问题只计算一次向量, 按余弦相似度合并排序,
+    /// 内容相同的切块只保留相似度最高的一条, 返回前 n_results 条
+    pub async fn query_collections(
+        &self,
+        coll_names: &[String],
+        text: &str,
+        where_metadata: Option<Value>,
+    ) -> anyhow::Result<Vec<Hit>> {
+        let (coll_names, search_all) = match coll_names.is_empty() {
+            true => (self.list_collections().await?.iter().map(|c| c.name().to_string()).collect(), true),
+            false => (coll_names.to_vec(), false),
+        };
+        let embedding = self.embed(&[text]).await?.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("向量服务没有返回问题的向量"))?;
+        let results = futures::future::join_all(coll_names.iter().map(|name| {
+            self.query_collection(name, text, &embedding, where_metadata.clone())
+        })).await;
+
+        let mut hits: Vec<Hit> = Vec::new();
+        for (name, result) in coll_names.iter().zip(results) {
+            let result = match result {
+                Ok(result) => result,
+                // 检索全部时跳过无法查询的集合, 如使用其他向量模型的集合
+                Err(err) if search_all => {
+                    eprintln!("跳过集合 {}: {}", name, err);
+                    continue;
+                },
+                Err(err) => return Err(err.context(format!("查询集合 {} 失败", name))),
+            };
+            hits.extend(result);
+        }
+
+        Ok(merge_hits(hits, self.n_results))
+    }
+
+    // Used ONLY for LLM call
+    pub async fn query_collections_llm(&self, params: HashMap<String, String>) -> anyhow::Result<String> {
+        let text = match params.get("text") {
+            Some(t) => t,
+            None => return Err(anyhow::anyhow!("参数'text'是必要的，表示你需要查找的文本的内容")),
+        };
+        let coll_names: Vec<String> = params.get("collection_names")
+            .map(|names| names.split([',', '，']).map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect())
+            .unwrap_or_default();
+        let where_metadata = parse_where(&params)?;
+
+        let hits = self.query_collections(&coll_names, text, where_metadata).await?;
+        describe_hits(&hits, true)
     }
 }
 
@@ -280,12 +1164,153 @@ mod tests {
             "test-3",
         ];
         let store = VectorStore::from_config(&config).await?;
-        match store.add("test", ids, documents, None).await {
+        match store.add("test", ids, documents, None, None).await {
             Ok(_) => Ok(()),
             Err(err) => panic!("{}", err.to_string())
         }
     }   
 
+    // 使用本地存储与本地向量模型的实例, 不依赖外部服务
+    async fn local_store(dir: &Path) -> anyhow::Result<VectorStore> {
+        let toml = format!(r#"
+            db_url = ""
+            vector_backend = "local"
+            local_store_dir = "{}"
+            embedding_provider = "local"
+            embedding_dim = 64
+            keyword_weight = 1.0
+            n_results = 3
+            chunk_size = 300
+            batch = 16
+            zhipu_url = ""
+            zhipu_embedding_model = ""
+            zhipu_api_key = ""
+            deepseek_url = ""
+            deepseek_chat_model = ""
+            deepseek_api_key = ""
+            system_prompt = ""
+        "#, dir.display());
+        let config = config::Config::builder()
+            .add_source(config::File::from_str(&toml, config::FileFormat::Toml))
+            .build()?
+            .try_deserialize::<Config>()?;
+        VectorStore::from_config(&config).await
+    }
+
+    #[tokio::test]
+    async fn test_keyword_index_invalidation() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("docster-keyword-{}", std::process::id()));
+        let store = local_store(&dir).await?;
+        let tokenizer = store.tokenizer.as_ref().unwrap();
+
+        store.add("kw", vec!["a-0"], vec!["苹果的产量"], None, None).await?;
+        assert!(store.keyword_index("kw", tokenizer).await?.search("香蕉", tokenizer, 3).is_empty());
+
+        // 记录数不变, 内容被替换
+        store.add("kw", vec!["a-0"], vec!["香蕉的产量"], None, None).await?;
+        let hits = store.keyword_index("kw", tokenizer).await?.search("香蕉", tokenizer, 3);
+        assert_eq!(hits.first().map(|(id, _)| id.as_str()), Some("a-0"));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collection_lookup() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("docster-lookup-{}", std::process::id()));
+        let store = local_store(&dir).await?;
+
+        // 读取不存在的集合时报错, 不会创建
+        assert!(store.get_page("missing", 0, 10, &["documents"]).await.is_err());
+        assert!(store.query_text("missing", vec!["产量"], None).await.is_err());
+        assert!(store.get_by_ids("missing", vec!["a-0".to_string()], &["documents"]).await.is_err());
+        assert!(store.get_where("missing", serde_json::json!({"source": "a.pdf"}), &["documents"]).await.is_err());
+        assert!(store.list_collections().await?.is_empty());
+
+        // 旧版本创建的集合在首次写入时补写向量模型与维度
+        store.client.create_collection("legacy", Map::new()).await?;
+        store.add("legacy", vec!["a-0"], vec!["苹果的产量"], None, None).await?;
+        let metadata = store.collection_metadata("legacy").await?.unwrap_or_default();
+        assert_eq!(metadata.get("embedding_model").and_then(|m| m.as_str()), Some(store.embedding_cli.model()));
+        assert_eq!(metadata.get("embedding_dim").and_then(|d| d.as_u64()), Some(64));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_collections() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("docster-multi-{}", std::process::id()));
+        let store = local_store(&dir).await?;
+        store.add("c1", vec!["a-0", "a-1"], vec!["苹果的产量", "香蕉的价格"], None, None).await?;
+        store.add("c2", vec!["b-0"], vec!["苹果的价格"], None, None).await?;
+
+        // 开启了关键词检索, 合并时仍按与问题向量的余弦相似度排序
+        let hits = store.query_collections(&["c1".to_string(), "c2".to_string()], "苹果", None).await?;
+        let query = store.embed(&["苹果"]).await?.remove(0);
+        for hit in &hits {
+            let result = store.get_by_ids(&hit.collection, vec![hit.id.clone()], &["embeddings"]).await?;
+            let vector = result.embeddings.and_then(|e| e.into_iter().next().flatten()).unwrap();
+            assert!((hit.score - similarity(local::cosine_distance(&query, &vector))).abs() < 1e-6);
+        }
+        assert!(hits.windows(2).all(|pair| pair[0].score >= pair[1].score));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_hits() {
+        let hit = |collection: &str, id: &str, document: &str, distance: f32| Hit {
+            collection: collection.to_string(),
+            id: id.to_string(),
+            document: document.to_string(),
+            metadata: None,
+            distance,
+            score: similarity(distance),
+        };
+        // far 只有一条且离得很远, 不能因为是该集合的第一名而排在 near 的第二名之前
+        let hits = vec![
+            hit("near", "n-0", "营收增长", 0.1),
+            hit("near", "n-1", "利润增长", 0.2),
+            hit("far", "f-0", "员工手册", 0.8),
+            hit("copy", "c-0", "营收增长", 0.15),
+        ];
+        let merged = merge_hits(hits, 3);
+        let ids = merged.iter().map(|hit| hit.id.as_str()).collect::<Vec<&str>>();
+        assert_eq!(ids, vec!["n-0", "n-1", "f-0"]);
+        assert!(merged[2].score < merged[1].score);
+    }
+
+    #[test]
+    fn test_describe_hits() -> anyhow::Result<()> {
+        let metadata = serde_json::json!({"source": "a.pdf", "page": 3}).as_object().cloned();
+        let hit = Hit {
+            collection: "finance".to_string(),
+            id: "a-0".to_string(),
+            document: "营收增长".to_string(),
+            metadata,
+            distance: 0.25,
+            score: 0.5,
+        };
+        // 输出的是 `score`(如跨集合合并时的余弦相似度), 不再由距离换算
+        let output = describe_hits(&[hit], false)?;
+        let entries: Value = serde_json::from_str(&output[output.find('[').unwrap()..])?;
+        assert_eq!(entries, serde_json::json!([
+            {"id": "a-0", "source": "a.pdf", "page": 3, "score": 0.5, "text": "营收增长"}
+        ]));
+
+        let missing = QueryResult {
+            ids: vec![vec!["a-0".to_string()]],
+            documents: None,
+            metadatas: None,
+            embeddings: None,
+            distances: None,
+        };
+        assert!(first_row_hits("finance", missing).is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_list_collection() -> anyhow::Result<()> {
         let config = read_config()?;
@@ -298,7 +1323,7 @@ mod tests {
     async fn test_query() {
         let config = read_config().unwrap();
         let store = VectorStore::from_config(&config).await.unwrap();
-        let result = store.query_text("coffee", vec!["咖啡水洗处理法"]).await.unwrap();
+        let result = store.query_text("coffee", vec!["咖啡水洗处理法"], None).await.unwrap();
         println!("{:?}", result.documents)
     }
 
@@ -0,0 +1,55 @@
+use async_trait::async_trait;
+use chromadb::collection::{CollectionEntries, GetOptions, GetResult, QueryOptions, QueryResult};
+use serde_json::{Map, Value};
+
+/// 集合的名称与元数据
+#[derive(Debug, Clone)]
+pub struct CollectionInfo {
+    name: String,
+    metadata: Option<Map<String, Value>>,
+}
+
+impl CollectionInfo {
+    pub fn new(name: &str, metadata: Option<Map<String, Value>>) -> Self {
+        Self { name: name.to_string(), metadata }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn metadata(&self) -> Option<&Map<String, Value>> {
+        self.metadata.as_ref()
+    }
+}
+
+/// 向量数据库的存储接口, 参数与返回值沿用Chroma的结构,
+/// `include` 为空时与Chroma的默认值一致
+#[async_trait]
+pub trait Backend: Send + Sync {
+    async fn list_collections(&self) -> anyhow::Result<Vec<CollectionInfo>>;
+
+    /// 集合不存在时返回错误
+    async fn get_collection(&self, name: &str) -> anyhow::Result<CollectionInfo>;
+
+    /// 集合已存在时直接返回, 不修改其元数据
+    async fn create_collection(&self, name: &str, metadata: Map<String, Value>) -> anyhow::Result<CollectionInfo>;
+
+    /// 重命名集合, 或用 `metadata` 整体替换集合元数据
+    async fn modify_collection(
+        &self,
+        name: &str,
+        new_name: Option<&str>,
+        metadata: Option<&Map<String, Value>>,
+    ) -> anyhow::Result<()>;
+
+    async fn delete_collection(&self, name: &str) -> anyhow::Result<()>;
+
+    async fn count(&self, name: &str) -> anyhow::Result<usize>;
+
+    async fn upsert(&self, name: &str, entries: CollectionEntries<'_>) -> anyhow::Result<()>;
+
+    async fn get(&self, name: &str, options: GetOptions) -> anyhow::Result<GetResult>;
+
+    async fn query(&self, name: &str, options: QueryOptions<'_>) -> anyhow::Result<QueryResult>;
+}
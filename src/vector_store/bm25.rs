@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use crate::document::tokenizer::Tokenizer;
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// 集合的BM25关键词索引, 弥补向量检索对型号、代码等精确词的不足
+pub struct Bm25Index {
+    ids: Vec<String>,
+    lengths: Vec<usize>,
+    // 词 -> (文档序号, 词频)
+    postings: HashMap<String, Vec<(usize, u32)>>,
+    avgdl: f32,
+}
+
+impl Bm25Index {
+    pub fn build<'a>(documents: impl IntoIterator<Item = (&'a str, &'a str)>, tokenizer: &Tokenizer) -> Self {
+        let mut index = Self { ids: Vec::new(), lengths: Vec::new(), postings: HashMap::new(), avgdl: 0.0 };
+        for (id, document) in documents {
+            let doc = index.ids.len();
+            let tokens = tokenizer.tokenize(document);
+            let mut freqs: HashMap<String, u32> = HashMap::new();
+            for token in &tokens {
+                *freqs.entry(token.clone()).or_default() += 1;
+            }
+            for (token, freq) in freqs {
+                index.postings.entry(token).or_default().push((doc, freq));
+            }
+            index.ids.push(id.to_string());
+            index.lengths.push(tokens.len());
+        }
+        let total = index.lengths.iter().sum::<usize>();
+        index.avgdl = total as f32 / index.ids.len().max(1) as f32;
+        index
+    }
+
+    /// 返回得分最高的 `n` 个文档ID及BM25得分
+    pub fn search(&self, query: &str, tokenizer: &Tokenizer, n: usize) -> Vec<(String, f32)> {
+        let total = self.ids.len() as f32;
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        let mut terms = tokenizer.tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        for term in terms {
+            let postings = match self.postings.get(&term) {
+                Some(postings) => postings,
+                None => continue,
+            };
+            let df = postings.len() as f32;
+            let idf = ((total - df + 0.5) / (df + 0.5) + 1.0).ln();
+            for &(doc, freq) in postings {
+                let tf = freq as f32;
+                let norm = K1 * (1.0 - B + B * self.lengths[doc] as f32 / self.avgdl.max(1.0));
+                *scores.entry(doc).or_default() += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut ranked = scores.into_iter().collect::<Vec<(usize, f32)>>();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked.into_iter().take(n).map(|(doc, score)| (self.ids[doc].clone(), score)).collect()
+    }
+}
+
+/// 加权的倒数排名融合(RRF), 返回按得分降序的ID
+pub fn fuse(rankings: &[(&[String], f32)], k: f32) -> Vec<(String, f32)> {
+    let mut scores: Vec<(String, f32)> = Vec::new();
+    for (ranking, weight) in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            let score = weight / (k + rank as f32 + 1.0);
+            match scores.iter_mut().find(|(other, _)| other == id) {
+                Some((_, total)) => *total += score,
+                None => scores.push((id.clone(), score)),
+            }
+        }
+    }
+    // 稳定排序, 得分相同时保留先出现的顺序
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bm25() {
+        let tokenizer = Tokenizer::new();
+        let documents = [
+            ("a-0", "公司DDR5内存产品出货量大幅增长"),
+            ("b-0", "先进封装方面, FCBGA基板产能持续扩张"),
+            ("c-0", "存储芯片价格回暖, 下游需求复苏"),
+        ];
+        let index = Bm25Index::build(documents, &tokenizer);
+        assert_eq!(index.search("FCBGA", &tokenizer, 3)[0].0, "b-0");
+        assert_eq!(index.search("ddr5 出货", &tokenizer, 3)[0].0, "a-0");
+        assert!(index.search("光刻机", &tokenizer, 3).is_empty());
+    }
+
+    #[test]
+    fn test_fuse() {
+        let vector = ["a".to_string(), "b".to_string(), "c".to_string()];
+        let keyword = ["c".to_string(), "d".to_string()];
+        let fused = fuse(&[(&vector, 1.0), (&keyword, 1.0)], 60.0);
+        let ids = fused.iter().map(|(id, _)| id.as_str()).collect::<Vec<&str>>();
+        assert_eq!(ids, vec!["c", "a", "b", "d"]);
+
+        let fused = fuse(&[(&vector, 1.0), (&keyword, 0.0)], 60.0);
+        assert_eq!(fused[0].0, "a");
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use chromadb::client::{ChromaAuthMethod, ChromaClient, ChromaClientOptions};
+use chromadb::collection::{ChromaCollection, CollectionEntries, GetOptions, GetResult, QueryOptions, QueryResult};
+use serde_json::{Map, Value};
+use super::backend::{Backend, CollectionInfo};
+use super::local::cosine_distance;
+use super::reorder_row;
+
+// 新集合与本地及pgvector一致使用余弦距离, 旧集合为Chroma默认的平方L2距离
+const SPACE_KEY: &str = "hnsw:space";
+const QUERY_INCLUDE: [&str; 3] = ["metadatas", "documents", "distances"];
+
+/// 连接Chroma服务的存储
+pub struct ChromaBackend {
+    client: ChromaClient,
+}
+
+fn info(collection: &ChromaCollection) -> CollectionInfo {
+    CollectionInfo::new(collection.name(), collection.metadata().cloned())
+}
+
+// Chroma要求 `where` 的每一层只有一个键, 多个字段的条件改写为 `$and`
+fn normalize_where(filter: Value) -> Value {
+    match filter {
+        Value::Object(map) if map.len() > 1 => Value::Object(Map::from_iter([(
+            "$and".to_string(),
+            Value::Array(map.into_iter().map(|(k, v)| normalize_where(Value::Object(Map::from_iter([(k, v)])))).collect()),
+        )])),
+        Value::Object(map) => Value::Object(map.into_iter().map(|(k, v)| match k.as_str() {
+            "$and" | "$or" => match v {
+                Value::Array(items) => (k, Value::Array(items.into_iter().map(normalize_where).collect())),
+                other => (k, other),
+            },
+            _ => (k, v),
+        }).collect()),
+        other => other,
+    }
+}
+
+// 用查询向量与结果向量重新计算余弦距离, 并按新的距离重排每一行
+fn to_cosine(result: &mut QueryResult, queries: &[Vec<f32>]) {
+    let (Some(embeddings), Some(distances)) = (&result.embeddings, result.distances.as_mut()) else {
+        return;
+    };
+    for ((row, query), embeds) in distances.iter_mut().zip(queries).zip(embeddings) {
+        *row = embeds.iter().map(|embed| cosine_distance(query, embed)).collect();
+    }
+    let orders = distances.iter().map(|row| {
+        let mut order = (0..row.len()).collect::<Vec<usize>>();
+        order.sort_by(|&a, &b| row[a].total_cmp(&row[b]));
+        order
+    }).collect::<Vec<Vec<usize>>>();
+    for (row, order) in orders.iter().enumerate() {
+        reorder_row(result, row, order);
+    }
+}
+
+impl ChromaBackend {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let auth = ChromaAuthMethod::None;
+        let client = ChromaClient::new(
+            ChromaClientOptions { url: Some(url.to_string()), auth, ..Default::default() }
+        ).await.with_context(|| "Database Connection Failed")?;
+        Ok(Self { client })
+    }
+
+    async fn collection(&self, name: &str) -> anyhow::Result<ChromaCollection> {
+        self.client.get_collection(name).await
+            .with_context(|| format!("Cannot find {}", name))
+    }
+}
+
+#[async_trait]
+impl Backend for ChromaBackend {
+    async fn list_collections(&self) -> anyhow::Result<Vec<CollectionInfo>> {
+        Ok(self.client.list_collections().await?.iter().map(info).collect())
+    }
+
+    async fn get_collection(&self, name: &str) -> anyhow::Result<CollectionInfo> {
+        Ok(info(&self.collection(name).await?))
+    }
+
+    async fn create_collection(&self, name: &str, mut metadata: Map<String, Value>) -> anyhow::Result<CollectionInfo> {
+        metadata.entry(SPACE_KEY).or_insert(Value::from("cosine"));
+        Ok(info(&self.client.get_or_create_collection(name, Some(metadata)).await?))
+    }
+
+    async fn modify_collection(
+        &self,
+        name: &str,
+        new_name: Option<&str>,
+        metadata: Option<&Map<String, Value>>,
+    ) -> anyhow::Result<()> {
+        // Chroma不允许修改距离函数等索引参数
+        let metadata = metadata.map(|metadata| {
+            metadata.iter()
+                .filter(|(key, _)| !key.starts_with("hnsw:"))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<Map<String, Value>>()
+        });
+        self.collection(name).await?.modify(new_name, metadata.as_ref()).await
+    }
+
+    async fn delete_collection(&self, name: &str) -> anyhow::Result<()> {
+        self.client.delete_collection(name).await
+            .with_context(|| format!("Cannot remove {}", name))
+    }
+
+    async fn count(&self, name: &str) -> anyhow::Result<usize> {
+        self.collection(name).await?.count().await
+    }
+
+    async fn upsert(&self, name: &str, entries: CollectionEntries<'_>) -> anyhow::Result<()> {
+        self.collection(name).await?.upsert(entries, None).await?;
+        Ok(())
+    }
+
+    async fn get(&self, name: &str, mut options: GetOptions) -> anyhow::Result<GetResult> {
+        options.where_metadata = options.where_metadata.map(normalize_where);
+        self.collection(name).await?.get(options).await
+    }
+
+    async fn query(&self, name: &str, mut options: QueryOptions<'_>) -> anyhow::Result<QueryResult> {
+        options.where_metadata = options.where_metadata.map(normalize_where);
+        let collection = self.collection(name).await?;
+        let space = collection.metadata().and_then(|m| m.get(SPACE_KEY)).and_then(|s| s.as_str());
+        let include = options.include.clone().unwrap_or(QUERY_INCLUDE.to_vec());
+        let queries = match (&options.query_embeddings, space) {
+            (Some(queries), None | Some("l2")) if include.contains(&"distances") => queries.clone(),
+            _ => return collection.query(options, None).await,
+        };
+
+        // 旧集合需要取回向量换算为余弦距离, 使各后端的距离含义一致
+        let with_embeddings = include.contains(&"embeddings");
+        let mut include = include;
+        if !with_embeddings {
+            include.push("embeddings");
+        }
+        options.include = Some(include);
+        let mut result = collection.query(options, None).await?;
+        to_cosine(&mut result, &queries);
+        if !with_embeddings {
+            result.embeddings = None;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_cosine() {
+        let mut result = QueryResult {
+            ids: vec![vec!["a".to_string(), "b".to_string()]],
+            metadatas: None,
+            documents: Some(vec![vec!["A".to_string(), "B".to_string()]]),
+            embeddings: Some(vec![vec![vec![3.0, 0.0], vec![0.0, 0.5]]]),
+            distances: Some(vec![vec![4.0, 1.25]]),
+        };
+        to_cosine(&mut result, &[vec![0.0, 1.0]]);
+        assert_eq!(result.ids, vec![vec!["b".to_string(), "a".to_string()]]);
+        assert_eq!(result.documents, Some(vec![vec!["B".to_string(), "A".to_string()]]));
+        assert_eq!(result.distances, Some(vec![vec![0.0, 1.0]]));
+    }
+
+    #[test]
+    fn test_normalize_where() {
+        assert_eq!(normalize_where(json!({"source": "a.pdf"})), json!({"source": "a.pdf"}));
+        assert_eq!(
+            normalize_where(json!({"department": "finance", "year": {"$gte": 2023}})),
+            json!({"$and": [{"department": "finance"}, {"year": {"$gte": 2023}}]}),
+        );
+        assert_eq!(
+            normalize_where(json!({"$or": [{"a": 1, "b": 2}, {"c": 3}]})),
+            json!({"$or": [{"$and": [{"a": 1}, {"b": 2}]}, {"c": 3}]}),
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::cmp::Ordering;
+use serde_json::{Map, Value};
+
+// 数值与字符串之间的比较, 其他类型不可比较
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn equals(a: &Value, b: &Value) -> bool {
+    compare(a, b).map_or(a == b, |ordering| ordering == Ordering::Equal)
+}
+
+fn match_operator(op: &str, operand: &Value, value: Option<&Value>) -> anyhow::Result<bool> {
+    let list = || operand.as_array().ok_or_else(|| anyhow::anyhow!("{} 的参数必须是数组", op));
+    Ok(match op {
+        "$eq" => value.is_some_and(|v| equals(v, operand)),
+        "$ne" => !value.is_some_and(|v| equals(v, operand)),
+        "$gt" => value.and_then(|v| compare(v, operand)) == Some(Ordering::Greater),
+        "$gte" => value.and_then(|v| compare(v, operand)).is_some_and(|o| o != Ordering::Less),
+        "$lt" => value.and_then(|v| compare(v, operand)) == Some(Ordering::Less),
+        "$lte" => value.and_then(|v| compare(v, operand)).is_some_and(|o| o != Ordering::Greater),
+        "$in" => value.is_some_and(|v| list().map(|l| l.iter().any(|o| equals(v, o))).unwrap_or(false)),
+        "$nin" => {
+            let list = list()?;
+            !value.is_some_and(|v| list.iter().any(|o| equals(v, o)))
+        },
+        _ => anyhow::bail!("不支持的过滤条件: {}", op),
+    })
+}
+
+/// 按Chroma的 `where` 语法判断元数据是否满足条件, 如
+/// `{"$and": [{"source": "a.pdf"}, {"timestamp": {"$gte": 1700000000}}]}`
+pub fn matches(filter: &Value, metadata: Option<&Map<String, Value>>) -> anyhow::Result<bool> {
+    let filter = filter.as_object().ok_or_else(|| anyhow::anyhow!("过滤条件必须是JSON对象"))?;
+    for (key, condition) in filter {
+        let matched = match key.as_str() {
+            "$and" | "$or" => {
+                let items = condition.as_array()
+                    .ok_or_else(|| anyhow::anyhow!("{} 的参数必须是数组", key))?;
+                let results = items.iter()
+                    .map(|item| matches(item, metadata))
+                    .collect::<anyhow::Result<Vec<bool>>>()?;
+                if key == "$and" { results.iter().all(|&r| r) } else { results.iter().any(|&r| r) }
+            },
+            _ => {
+                let value = metadata.and_then(|m| m.get(key));
+                match condition {
+                    Value::Object(ops) => {
+                        let mut matched = true;
+                        for (op, operand) in ops {
+                            matched &= match_operator(op, operand, value)?;
+                        }
+                        matched
+                    },
+                    _ => match_operator("$eq", condition, value)?,
+                }
+            },
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// 文档内容过滤, 支持 `$contains` 与 `$not_contains`
+pub fn matches_document(filter: &Value, document: &str) -> anyhow::Result<bool> {
+    let filter = filter.as_object().ok_or_else(|| anyhow::anyhow!("过滤条件必须是JSON对象"))?;
+    for (op, operand) in filter {
+        let matched = match (op.as_str(), operand) {
+            ("$contains", Value::String(text)) => document.contains(text.as_str()),
+            ("$not_contains", Value::String(text)) => !document.contains(text.as_str()),
+            _ => anyhow::bail!("不支持的文档过滤条件: {}", op),
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_matches() -> anyhow::Result<()> {
+        let metadata = json!({"source": "a.pdf", "year": 2023, "tag": "年报"});
+        let metadata = metadata.as_object();
+
+        assert!(matches(&json!({"source": "a.pdf"}), metadata)?);
+        assert!(!matches(&json!({"source": "b.pdf"}), metadata)?);
+        assert!(matches(&json!({"year": {"$gte": 2023, "$lt": 2024}}), metadata)?);
+        assert!(matches(&json!({"year": {"$in": [2022, 2023]}}), metadata)?);
+        assert!(matches(&json!({"author": {"$ne": "张三"}}), metadata)?);
+        assert!(matches(&json!({"$or": [{"tag": "季报"}, {"year": {"$gt": 2020}}]}), metadata)?);
+        assert!(!matches(&json!({"$and": [{"tag": "年报"}, {"year": {"$lt": 2020}}]}), metadata)?);
+        assert!(matches(&json!({"year": {"$like": 1}}), metadata).is_err());
+
+        assert!(matches_document(&json!({"$contains": "营收"}), "公司营收增长")?);
+        assert!(!matches_document(&json!({"$not_contains": "营收"}), "公司营收增长")?);
+        Ok(())
+    }
+}
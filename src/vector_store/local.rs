@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chromadb::collection::{CollectionEntries, GetOptions, GetResult, QueryOptions, QueryResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use super::backend::{Backend, CollectionInfo};
+use super::{filter, Record};
+
+pub const DEFAULT_DIR: &str = ".docster/store";
+const META_FILE: &str = "collection.json";
+const RECORDS_FILE: &str = "records.jsonl";
+const DEFAULT_N_RESULTS: usize = 10;
+// 追加日志中的过期行超过该数量且多于有效记录时重写文件
+const COMPACT_MIN_LINES: usize = 1000;
+
+/// 保存在本地目录中的向量存储, 无需启动数据库服务
+///
+/// 每个集合一个目录: `collection.json` 保存元数据, `records.jsonl` 为追加写入的记录日志,
+/// 同一ID以最后一行为准. 查询使用暴力检索(余弦距离), 数万个切块以内足够快
+pub struct LocalBackend {
+    dir: PathBuf,
+    collections: Mutex<HashMap<String, LocalCollection>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CollectionFile {
+    name: String,
+    metadata: Option<Map<String, Value>>,
+}
+
+struct LocalCollection {
+    metadata: Option<Map<String, Value>>,
+    records: Vec<Record>,
+    index: HashMap<String, usize>,
+    // records.jsonl 的行数, 包括被覆盖的旧记录
+    lines: usize,
+}
+
+pub(super) fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 { 1.0 } else { 1.0 - dot / norm }
+}
+
+fn included(include: &Option<Vec<String>>, field: &str, default: &[&str]) -> bool {
+    match include {
+        Some(include) => include.iter().any(|f| f == field),
+        None => default.contains(&field),
+    }
+}
+
+fn write_meta(dir: &Path, name: &str, metadata: Option<&Map<String, Value>>) -> anyhow::Result<()> {
+    let file = CollectionFile { name: name.to_string(), metadata: metadata.cloned() };
+    fs::write(dir.join(META_FILE), serde_json::to_string_pretty(&file)?)?;
+    Ok(())
+}
+
+impl LocalCollection {
+    fn load(dir: &Path) -> anyhow::Result<Self> {
+        let meta: CollectionFile = serde_json::from_str(&fs::read_to_string(dir.join(META_FILE))?)?;
+        let mut collection = Self { metadata: meta.metadata, records: Vec::new(), index: HashMap::new(), lines: 0 };
+
+        let path = dir.join(RECORDS_FILE);
+        if path.exists() {
+            for (i, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() { continue }
+                let record: Record = serde_json::from_str(&line)
+                    .map_err(|err| anyhow::anyhow!("{} 第{}行无法解析: {}", path.display(), i + 1, err))?;
+                collection.insert(record);
+                collection.lines += 1;
+            }
+        }
+        Ok(collection)
+    }
+
+    fn insert(&mut self, record: Record) {
+        match self.index.get(&record.id) {
+            Some(&i) => self.records[i] = record,
+            None => {
+                self.index.insert(record.id.clone(), self.records.len());
+                self.records.push(record);
+            },
+        }
+    }
+
+    fn append(&mut self, dir: &Path, records: Vec<Record>) -> anyhow::Result<()> {
+        let file = File::options().create(true).append(true).open(dir.join(RECORDS_FILE))?;
+        let mut writer = BufWriter::new(file);
+        for record in &records {
+            writeln!(writer, "{}", serde_json::to_string(record)?)?;
+        }
+        writer.flush()?;
+        self.lines += records.len();
+        for record in records {
+            self.insert(record);
+        }
+
+        if self.lines > COMPACT_MIN_LINES && self.lines > self.records.len() * 2 {
+            self.compact(dir)?;
+        }
+        Ok(())
+    }
+
+    // 只保留每个ID的最新记录, 先写临时文件再替换
+    fn compact(&mut self, dir: &Path) -> anyhow::Result<()> {
+        let tmp = dir.join(format!("{}.tmp", RECORDS_FILE));
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        for record in &self.records {
+            writeln!(writer, "{}", serde_json::to_string(record)?)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(tmp, dir.join(RECORDS_FILE))?;
+        self.lines = self.records.len();
+        Ok(())
+    }
+
+    fn filtered(&self, where_metadata: &Option<Value>, where_document: &Option<Value>) -> anyhow::Result<Vec<&Record>> {
+        let mut records = Vec::new();
+        for record in &self.records {
+            if let Some(filter) = where_metadata {
+                if !filter::matches(filter, record.metadata.as_ref())? { continue }
+            }
+            if let Some(filter) = where_document {
+                if !filter::matches_document(filter, &record.document)? { continue }
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+impl LocalBackend {
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.to_path_buf(), collections: Mutex::new(HashMap::new()) })
+    }
+
+    fn path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            anyhow::bail!("集合名称不合法: {}", name);
+        }
+        Ok(self.dir.join(name))
+    }
+
+    fn exists(&self, name: &str) -> anyhow::Result<bool> {
+        Ok(self.path(name)?.join(META_FILE).exists())
+    }
+
+    // 首次访问时从磁盘加载集合
+    fn with_collection<T>(
+        &self,
+        name: &str,
+        f: impl FnOnce(&mut LocalCollection, &Path) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let path = self.path(name)?;
+        let mut collections = self.collections.lock().unwrap();
+        if !collections.contains_key(name) {
+            if !self.exists(name)? {
+                anyhow::bail!("Cannot find {}", name);
+            }
+            collections.insert(name.to_string(), LocalCollection::load(&path)?);
+        }
+        f(collections.get_mut(name).unwrap(), &path)
+    }
+}
+
+#[async_trait]
+impl Backend for LocalBackend {
+    async fn list_collections(&self) -> anyhow::Result<Vec<CollectionInfo>> {
+        let mut names = fs::read_dir(&self.dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().join(META_FILE).exists())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        names.sort();
+
+        let mut collections = Vec::with_capacity(names.len());
+        for name in names {
+            let meta: CollectionFile = serde_json::from_str(&fs::read_to_string(self.path(&name)?.join(META_FILE))?)?;
+            collections.push(CollectionInfo::new(&name, meta.metadata));
+        }
+        Ok(collections)
+    }
+
+    async fn get_collection(&self, name: &str) -> anyhow::Result<CollectionInfo> {
+        self.with_collection(name, |collection, _| Ok(CollectionInfo::new(name, collection.metadata.clone())))
+    }
+
+    async fn create_collection(&self, name: &str, metadata: Map<String, Value>) -> anyhow::Result<CollectionInfo> {
+        if !self.exists(name)? {
+            let path = self.path(name)?;
+            fs::create_dir_all(&path)?;
+            write_meta(&path, name, (!metadata.is_empty()).then_some(&metadata))?;
+        }
+        self.get_collection(name).await
+    }
+
+    async fn modify_collection(
+        &self,
+        name: &str,
+        new_name: Option<&str>,
+        metadata: Option<&Map<String, Value>>,
+    ) -> anyhow::Result<()> {
+        if let Some(metadata) = metadata {
+            self.with_collection(name, |collection, path| {
+                write_meta(path, name, Some(metadata))?;
+                collection.metadata = Some(metadata.clone());
+                Ok(())
+            })?;
+        }
+        if let Some(new_name) = new_name {
+            if self.exists(new_name)? {
+                anyhow::bail!("Collection {} already exists", new_name);
+            }
+            let metadata = self.get_collection(name).await?.metadata().cloned();
+            let mut collections = self.collections.lock().unwrap();
+            fs::rename(self.path(name)?, self.path(new_name)?)?;
+            write_meta(&self.path(new_name)?, new_name, metadata.as_ref())?;
+            if let Some(collection) = collections.remove(name) {
+                collections.insert(new_name.to_string(), collection);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_collection(&self, name: &str) -> anyhow::Result<()> {
+        if !self.exists(name)? {
+            anyhow::bail!("Cannot remove {}", name);
+        }
+        self.collections.lock().unwrap().remove(name);
+        fs::remove_dir_all(self.path(name)?)?;
+        Ok(())
+    }
+
+    async fn count(&self, name: &str) -> anyhow::Result<usize> {
+        self.with_collection(name, |collection, _| Ok(collection.records.len()))
+    }
+
+    async fn upsert(&self, name: &str, entries: CollectionEntries<'_>) -> anyhow::Result<()> {
+        let CollectionEntries { ids, metadatas, documents, embeddings } = entries;
+        let embeddings = embeddings.ok_or_else(|| anyhow::anyhow!("本地存储需要提供向量"))?;
+        if embeddings.len() != ids.len()
+            || documents.as_ref().is_some_and(|d| d.len() != ids.len())
+            || metadatas.as_ref().is_some_and(|m| m.len() != ids.len()) {
+            anyhow::bail!("ids, documents, metadatas, embeddings 的数量不一致");
+        }
+
+        self.with_collection(name, |collection, path| {
+            let mut metadatas = metadatas.map(|m| m.into_iter());
+            let records = ids.iter().zip(embeddings).enumerate().map(|(i, (id, embedding))| {
+                let document = match &documents {
+                    Some(documents) => documents[i].to_string(),
+                    // 未提供文档时保留原有内容
+                    None => collection.index.get(*id)
+                        .map(|&j| collection.records[j].document.clone())
+                        .unwrap_or_default(),
+                };
+                let metadata = metadatas.as_mut().and_then(|m| m.next());
+                Record { id: id.to_string(), document, metadata, embedding }
+            }).collect::<Vec<Record>>();
+            collection.append(path, records)
+        })
+    }
+
+    async fn get(&self, name: &str, options: GetOptions) -> anyhow::Result<GetResult> {
+        let GetOptions { ids, where_metadata, limit, offset, where_document, include } = options;
+        let default = ["metadatas", "documents"];
+
+        self.with_collection(name, |collection, _| {
+            let mut records = collection.filtered(&where_metadata, &where_document)?;
+            if !ids.is_empty() {
+                records.retain(|record| ids.contains(&record.id));
+            }
+            let records = records.into_iter()
+                .skip(offset.unwrap_or(0))
+                .take(limit.unwrap_or(usize::MAX))
+                .collect::<Vec<&Record>>();
+
+            Ok(GetResult {
+                ids: records.iter().map(|r| r.id.clone()).collect(),
+                metadatas: included(&include, "metadatas", &default)
+                    .then(|| records.iter().map(|r| r.metadata.clone()).collect()),
+                documents: included(&include, "documents", &default)
+                    .then(|| records.iter().map(|r| Some(r.document.clone())).collect()),
+                embeddings: included(&include, "embeddings", &default)
+                    .then(|| records.iter().map(|r| Some(r.embedding.clone())).collect()),
+            })
+        })
+    }
+
+    async fn query(&self, name: &str, options: QueryOptions<'_>) -> anyhow::Result<QueryResult> {
+        let queries = options.query_embeddings
+            .ok_or_else(|| anyhow::anyhow!("本地存储只支持以向量查询"))?;
+        let n_results = options.n_results.unwrap_or(DEFAULT_N_RESULTS);
+        let include = options.include.map(|i| i.iter().map(|s| s.to_string()).collect());
+        let default = ["metadatas", "documents", "distances"];
+
+        self.with_collection(name, |collection, _| {
+            let candidates = collection.filtered(&options.where_metadata, &options.where_document)?;
+            let mut rows = Vec::with_capacity(queries.len());
+            for query in &queries {
+                if let Some(record) = candidates.iter().find(|r| r.embedding.len() != query.len()) {
+                    anyhow::bail!("查询向量维度为 {}, 与集合中的 {} 不一致", query.len(), record.embedding.len());
+                }
+                let mut scored = candidates.iter()
+                    .map(|record| (cosine_distance(query, &record.embedding), *record))
+                    .collect::<Vec<(f32, &Record)>>();
+                scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+                scored.truncate(n_results);
+                rows.push(scored);
+            }
+
+            let column = |field: &str| included(&include, field, &default);
+            Ok(QueryResult {
+                ids: rows.iter().map(|row| row.iter().map(|(_, r)| r.id.clone()).collect()).collect(),
+                metadatas: column("metadatas")
+                    .then(|| rows.iter().map(|row| row.iter().map(|(_, r)| r.metadata.clone()).collect()).collect()),
+                documents: column("documents")
+                    .then(|| rows.iter().map(|row| row.iter().map(|(_, r)| r.document.clone()).collect()).collect()),
+                embeddings: column("embeddings")
+                    .then(|| rows.iter().map(|row| row.iter().map(|(_, r)| r.embedding.clone()).collect()).collect()),
+                distances: column("distances")
+                    .then(|| rows.iter().map(|row| row.iter().map(|(d, _)| *d).collect()).collect()),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_local_backend() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("docster-store-test-{}", std::process::id()));
+        let backend = LocalBackend::open(&dir)?;
+        backend.create_collection("reports", Map::new()).await?;
+
+        let metadatas = vec![json!({"source": "a.pdf"}), json!({"source": "b.pdf"}), json!({"source": "a.pdf"})]
+            .into_iter().map(|m| m.as_object().unwrap().clone()).collect();
+        let entries = CollectionEntries {
+            ids: vec!["a-0", "b-0", "a-1"],
+            metadatas: Some(metadatas),
+            documents: Some(vec!["营收", "利润", "现金流"]),
+            embeddings: Some(vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.7, 0.7]]),
+        };
+        backend.upsert("reports", entries).await?;
+
+        let query = QueryOptions { query_embeddings: Some(vec![vec![1.0, 0.1]]), n_results: Some(2), ..Default::default() };
+        let result = backend.query("reports", query).await?;
+        assert_eq!(result.ids[0], vec!["a-0", "a-1"]);
+
+        let options = GetOptions { where_metadata: Some(json!({"source": "b.pdf"})), ..Default::default() };
+        assert_eq!(backend.get("reports", options).await?.ids, vec!["b-0"]);
+
+        // 重新打开后从磁盘恢复, 重命名后旧名称不可用
+        let backend = LocalBackend::open(&dir)?;
+        backend.modify_collection("reports", Some("archive"), None).await?;
+        assert_eq!(backend.count("archive").await?, 3);
+        assert!(backend.count("reports").await.is_err());
+
+        backend.delete_collection("archive").await?;
+        assert!(backend.list_collections().await?.is_empty());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
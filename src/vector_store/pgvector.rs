@@ -0,0 +1,411 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use chromadb::collection::{CollectionEntries, GetOptions, GetResult, QueryOptions, QueryResult};
+use deadpool_postgres::Pool;
+use serde_json::{Map, Value};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Row;
+use super::backend::{Backend, CollectionInfo};
+
+const DEFAULT_N_RESULTS: usize = 10;
+// pgvector 的 vector 类型最多支持2000维的HNSW索引, 更高维度改用 halfvec
+const MAX_VECTOR_INDEX_DIM: usize = 2000;
+
+/// 保存在Postgres(pgvector扩展)中的向量存储, 与 `qa_pairs` 使用同一个数据库
+///
+/// `vector_collections` 记录集合的名称与元数据, 每个集合的记录保存在单独的
+/// `docster_vectors_<id>` 表中, 首次写入时按向量维度建表并创建HNSW索引
+pub struct PgvectorBackend {
+    pool: Pool,
+}
+
+type Param = Box<dyn ToSql + Sync + Send>;
+
+/// 将Chroma的 `where` 条件转换为SQL, 参数从 `params` 当前长度之后开始编号
+struct SqlFilter<'a> {
+    params: &'a mut Vec<Param>,
+}
+
+impl SqlFilter<'_> {
+    fn bind(&mut self, param: Param) -> String {
+        self.params.push(param);
+        format!("${}", self.params.len())
+    }
+
+    fn field(&mut self, key: &str) -> String {
+        format!("metadata -> {}::text", self.bind(Box::new(key.to_string())))
+    }
+
+    fn operator(&mut self, key: &str, op: &str, operand: &Value) -> anyhow::Result<String> {
+        let field = self.field(key);
+        let comparison = match op {
+            "$gt" => ">",
+            "$gte" => ">=",
+            "$lt" => "<",
+            "$lte" => "<=",
+            _ => "",
+        };
+        Ok(match (op, operand) {
+            ("$eq", _) => format!("{} = {}::text::jsonb", field, self.bind(Box::new(operand.to_string()))),
+            ("$ne", _) => format!("{} IS DISTINCT FROM {}::text::jsonb", field, self.bind(Box::new(operand.to_string()))),
+            (_, Value::Number(n)) if !comparison.is_empty() => format!(
+                "(jsonb_typeof({0}) = 'number' AND ({0})::text::float8 {1} {2}::float8)",
+                field, comparison, self.bind(Box::new(n.as_f64().unwrap_or_default()))
+            ),
+            (_, Value::String(s)) if !comparison.is_empty() => format!(
+                "(jsonb_typeof({0}) = 'string' AND ({0}) #>> '{{}}' {1} {2}::text)",
+                field, comparison, self.bind(Box::new(s.clone()))
+            ),
+            ("$in" | "$nin", Value::Array(items)) => {
+                let items = items.iter().map(|item| item.to_string()).collect::<Vec<String>>();
+                let any = format!("COALESCE({} = ANY({}::text[]::jsonb[]), false)", field, self.bind(Box::new(items)));
+                if op == "$in" { any } else { format!("NOT {}", any) }
+            },
+            _ => anyhow::bail!("不支持的过滤条件: {} {}", op, operand),
+        })
+    }
+
+    fn metadata(&mut self, filter: &Value) -> anyhow::Result<String> {
+        let filter = filter.as_object().ok_or_else(|| anyhow::anyhow!("过滤条件必须是JSON对象"))?;
+        let mut clauses = Vec::new();
+        for (key, condition) in filter {
+            let clause = match (key.as_str(), condition) {
+                ("$and" | "$or", Value::Array(items)) => {
+                    let items = items.iter()
+                        .map(|item| self.metadata(item))
+                        .collect::<anyhow::Result<Vec<String>>>()?;
+                    let joiner = if key == "$and" { " AND " } else { " OR " };
+                    format!("({})", items.join(joiner))
+                },
+                ("$and" | "$or", _) => anyhow::bail!("{} 的参数必须是数组", key),
+                (_, Value::Object(ops)) => {
+                    let ops = ops.iter()
+                        .map(|(op, operand)| self.operator(key, op, operand))
+                        .collect::<anyhow::Result<Vec<String>>>()?;
+                    format!("({})", ops.join(" AND "))
+                },
+                _ => self.operator(key, "$eq", condition)?,
+            };
+            clauses.push(clause);
+        }
+        Ok(if clauses.is_empty() { "TRUE".to_string() } else { clauses.join(" AND ") })
+    }
+
+    fn document(&mut self, filter: &Value) -> anyhow::Result<String> {
+        let filter = filter.as_object().ok_or_else(|| anyhow::anyhow!("过滤条件必须是JSON对象"))?;
+        let mut clauses = Vec::new();
+        for (op, operand) in filter {
+            let text = operand.as_str().ok_or_else(|| anyhow::anyhow!("{} 的参数必须是字符串", op))?;
+            let param = self.bind(Box::new(text.to_string()));
+            clauses.push(match op.as_str() {
+                "$contains" => format!("strpos(document, {}) > 0", param),
+                "$not_contains" => format!("strpos(document, {}) = 0", param),
+                _ => anyhow::bail!("不支持的文档过滤条件: {}", op),
+            });
+        }
+        Ok(if clauses.is_empty() { "TRUE".to_string() } else { clauses.join(" AND ") })
+    }
+
+    fn clause(&mut self, where_metadata: &Option<Value>, where_document: &Option<Value>) -> anyhow::Result<String> {
+        let mut clauses = Vec::new();
+        if let Some(filter) = where_metadata {
+            clauses.push(self.metadata(filter)?);
+        }
+        if let Some(filter) = where_document {
+            clauses.push(self.document(filter)?);
+        }
+        Ok(if clauses.is_empty() { "TRUE".to_string() } else { clauses.join(" AND ") })
+    }
+}
+
+fn table(id: i32) -> String {
+    format!("docster_vectors_{}", id)
+}
+
+// 查询时的距离表达式需要与索引一致才能走HNSW索引
+fn distance(dim: usize, param: &str) -> String {
+    if dim <= MAX_VECTOR_INDEX_DIM {
+        format!("embedding <=> {}::real[]::vector", param)
+    } else {
+        format!("embedding::halfvec({0}) <=> {1}::real[]::halfvec({0})", dim, param)
+    }
+}
+
+fn parse_metadata(text: Option<String>) -> anyhow::Result<Option<Map<String, Value>>> {
+    Ok(match text {
+        Some(text) => serde_json::from_str::<Value>(&text)?.as_object().cloned(),
+        None => None,
+    })
+}
+
+fn params(params: &[Param]) -> Vec<&(dyn ToSql + Sync)> {
+    params.iter().map(|p| p.as_ref() as &(dyn ToSql + Sync)).collect()
+}
+
+fn included(include: &Option<Vec<String>>, field: &str, default: &[&str]) -> bool {
+    match include {
+        Some(include) => include.iter().any(|f| f == field),
+        None => default.contains(&field),
+    }
+}
+
+impl PgvectorBackend {
+    pub async fn connect(pool: Pool) -> anyhow::Result<Self> {
+        let client = pool.get().await?;
+        // 创建扩展需要较高权限, 已安装时跳过, 普通用户也能连接
+        let installed = client.query_opt("SELECT 1 FROM pg_extension WHERE extname = 'vector'", &[]).await?.is_some();
+        if !installed {
+            client.batch_execute("CREATE EXTENSION IF NOT EXISTS vector").await
+                .context("无法安装pgvector扩展, 请由数据库管理员执行 CREATE EXTENSION vector")?;
+        }
+        client.batch_execute(
+            "CREATE TABLE IF NOT EXISTS vector_collections (
+                 id SERIAL PRIMARY KEY,
+                 name TEXT UNIQUE NOT NULL,
+                 metadata JSONB,
+                 dimension INT
+             );"
+        ).await?;
+        Ok(Self { pool })
+    }
+
+    // 返回集合的表ID与向量维度, 尚未写入过记录时维度为空
+    async fn collection(&self, name: &str) -> anyhow::Result<(i32, Option<usize>)> {
+        let client = self.pool.get().await?;
+        let row = client.query_opt("SELECT id, dimension FROM vector_collections WHERE name = $1", &[&name]).await?
+            .ok_or_else(|| anyhow::anyhow!("Cannot find {}", name))?;
+        Ok((row.get(0), row.get::<_, Option<i32>>(1).map(|d| d as usize)))
+    }
+
+    async fn create_table(&self, id: i32, dim: usize) -> anyhow::Result<()> {
+        let table = table(id);
+        let index = if dim <= MAX_VECTOR_INDEX_DIM {
+            "embedding vector_cosine_ops".to_string()
+        } else {
+            format!("(embedding::halfvec({})) halfvec_cosine_ops", dim)
+        };
+        let client = self.pool.get().await?;
+        client.batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
+                 seq BIGSERIAL,
+                 id TEXT PRIMARY KEY,
+                 document TEXT NOT NULL,
+                 metadata JSONB,
+                 embedding vector({dim}) NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS {table}_hnsw ON {table} USING hnsw ({index});
+             CREATE INDEX IF NOT EXISTS {table}_metadata ON {table} USING gin (metadata);"
+        )).await?;
+        client.execute("UPDATE vector_collections SET dimension = $1 WHERE id = $2", &[&(dim as i32), &id]).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Backend for PgvectorBackend {
+    async fn list_collections(&self) -> anyhow::Result<Vec<CollectionInfo>> {
+        let client = self.pool.get().await?;
+        let rows = client.query("SELECT name, metadata::text FROM vector_collections ORDER BY name", &[]).await?;
+        rows.iter()
+            .map(|row| Ok(CollectionInfo::new(row.get(0), parse_metadata(row.get(1))?)))
+            .collect()
+    }
+
+    async fn get_collection(&self, name: &str) -> anyhow::Result<CollectionInfo> {
+        let client = self.pool.get().await?;
+        let row = client.query_opt("SELECT metadata::text FROM vector_collections WHERE name = $1", &[&name]).await?
+            .ok_or_else(|| anyhow::anyhow!("Cannot find {}", name))?;
+        Ok(CollectionInfo::new(name, parse_metadata(row.get(0))?))
+    }
+
+    async fn create_collection(&self, name: &str, metadata: Map<String, Value>) -> anyhow::Result<CollectionInfo> {
+        let metadata = (!metadata.is_empty()).then(|| Value::Object(metadata).to_string());
+        let client = self.pool.get().await?;
+        client.execute(
+            "INSERT INTO vector_collections (name, metadata) VALUES ($1, $2::text::jsonb) ON CONFLICT (name) DO NOTHING",
+            &[&name, &metadata],
+        ).await?;
+        self.get_collection(name).await
+    }
+
+    async fn modify_collection(
+        &self,
+        name: &str,
+        new_name: Option<&str>,
+        metadata: Option<&Map<String, Value>>,
+    ) -> anyhow::Result<()> {
+        self.collection(name).await?;
+        let client = self.pool.get().await?;
+        if let Some(metadata) = metadata {
+            let metadata = Value::Object(metadata.clone()).to_string();
+            client.execute(
+                "UPDATE vector_collections SET metadata = $1::text::jsonb WHERE name = $2", &[&metadata, &name]
+            ).await?;
+        }
+        if let Some(new_name) = new_name {
+            // 记录表以ID命名, 重命名只需修改名称
+            client.execute("UPDATE vector_collections SET name = $1 WHERE name = $2", &[&new_name, &name]).await
+                .map_err(|err| match err.code() {
+                    Some(&SqlState::UNIQUE_VIOLATION) => anyhow::anyhow!("Collection {} already exists", new_name),
+                    _ => anyhow::Error::from(err),
+                })?;
+        }
+        Ok(())
+    }
+
+    async fn delete_collection(&self, name: &str) -> anyhow::Result<()> {
+        let (id, _) = self.collection(name).await
+            .map_err(|_| anyhow::anyhow!("Cannot remove {}", name))?;
+        let mut client = self.pool.get().await?;
+        let transaction = client.transaction().await?;
+        transaction.batch_execute(&format!("DROP TABLE IF EXISTS {}", table(id))).await?;
+        transaction.execute("DELETE FROM vector_collections WHERE id = $1", &[&id]).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn count(&self, name: &str) -> anyhow::Result<usize> {
+        let (id, dim) = self.collection(name).await?;
+        if dim.is_none() {
+            return Ok(0);
+        }
+        let client = self.pool.get().await?;
+        let row = client.query_one(&format!("SELECT count(*) FROM {}", table(id)), &[]).await?;
+        Ok(row.get::<_, i64>(0) as usize)
+    }
+
+    async fn upsert(&self, name: &str, entries: CollectionEntries<'_>) -> anyhow::Result<()> {
+        let CollectionEntries { ids, metadatas, documents, embeddings } = entries;
+        let embeddings = embeddings.ok_or_else(|| anyhow::anyhow!("pgvector需要提供向量"))?;
+        if embeddings.len() != ids.len()
+            || documents.as_ref().is_some_and(|d| d.len() != ids.len())
+            || metadatas.as_ref().is_some_and(|m| m.len() != ids.len()) {
+            anyhow::bail!("ids, documents, metadatas, embeddings 的数量不一致");
+        }
+        let (id, dim) = self.collection(name).await?;
+        if let (None, Some(embedding)) = (dim, embeddings.first()) {
+            self.create_table(id, embedding.len()).await?;
+        }
+
+        // 未提供文档时保留原有内容
+        let sql = format!(
+            "INSERT INTO {} (id, document, metadata, embedding) VALUES ($1, COALESCE($2, ''), $3::text::jsonb, $4::real[]::vector)
+             ON CONFLICT (id) DO UPDATE SET
+                 document = COALESCE($2, {0}.document), metadata = EXCLUDED.metadata, embedding = EXCLUDED.embedding",
+            table(id)
+        );
+        let mut client = self.pool.get().await?;
+        let transaction = client.transaction().await?;
+        let statement = transaction.prepare(&sql).await?;
+        for (i, (record_id, embedding)) in ids.iter().zip(&embeddings).enumerate() {
+            let document = documents.as_ref().map(|d| d[i]);
+            let metadata = metadatas.as_ref().map(|m| Value::Object(m[i].clone()).to_string());
+            transaction.execute(&statement, &[record_id, &document, &metadata, embedding]).await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn get(&self, name: &str, options: GetOptions) -> anyhow::Result<GetResult> {
+        let GetOptions { ids, where_metadata, limit, offset, where_document, include } = options;
+        let (id, dim) = self.collection(name).await?;
+        if dim.is_none() {
+            return Ok(GetResult { ids: vec![], metadatas: Some(vec![]), documents: Some(vec![]), embeddings: Some(vec![]) });
+        }
+
+        let mut values: Vec<Param> = Vec::new();
+        let mut filter = SqlFilter { params: &mut values };
+        let mut clause = filter.clause(&where_metadata, &where_document)?;
+        if !ids.is_empty() {
+            clause = format!("{} AND id = ANY({})", clause, filter.bind(Box::new(ids)));
+        }
+        let limit = limit.map(|l| format!(" LIMIT {}", l)).unwrap_or_default();
+        let sql = format!(
+            "SELECT id, document, metadata::text, embedding::real[] FROM {} WHERE {} ORDER BY seq{} OFFSET {}",
+            table(id), clause, limit, offset.unwrap_or(0)
+        );
+        let client = self.pool.get().await?;
+        let rows = client.query(&sql, &params(&values)).await?;
+
+        let default = ["metadatas", "documents"];
+        Ok(GetResult {
+            ids: rows.iter().map(|row| row.get(0)).collect(),
+            metadatas: included(&include, "metadatas", &default)
+                .then(|| rows.iter().map(|row| parse_metadata(row.get(2))).collect::<anyhow::Result<_>>())
+                .transpose()?,
+            documents: included(&include, "documents", &default)
+                .then(|| rows.iter().map(|row| Some(row.get(1))).collect()),
+            embeddings: included(&include, "embeddings", &default)
+                .then(|| rows.iter().map(|row| Some(row.get(3))).collect()),
+        })
+    }
+
+    async fn query(&self, name: &str, options: QueryOptions<'_>) -> anyhow::Result<QueryResult> {
+        let queries = options.query_embeddings
+            .ok_or_else(|| anyhow::anyhow!("pgvector只支持以向量查询"))?;
+        let n_results = options.n_results.unwrap_or(DEFAULT_N_RESULTS);
+        let (id, dim) = self.collection(name).await?;
+
+        let mut rows: Vec<Vec<Row>> = Vec::with_capacity(queries.len());
+        if let Some(dim) = dim {
+            let client = self.pool.get().await?;
+            for query in &queries {
+                if query.len() != dim {
+                    anyhow::bail!("查询向量维度为 {}, 与集合中的 {} 不一致", query.len(), dim);
+                }
+                let mut values: Vec<Param> = vec![Box::new(query.clone())];
+                let clause = SqlFilter { params: &mut values }.clause(&options.where_metadata, &options.where_document)?;
+                let sql = format!(
+                    "SELECT id, document, metadata::text, embedding::real[], ({})::real AS distance
+                     FROM {} WHERE {} ORDER BY distance LIMIT {}",
+                    distance(dim, "$1"), table(id), clause, n_results
+                );
+                rows.push(client.query(&sql, &params(&values)).await?);
+            }
+        } else {
+            rows.resize_with(queries.len(), Vec::new);
+        }
+
+        let default = ["metadatas", "documents", "distances"];
+        let include = options.include.map(|i| i.iter().map(|s| s.to_string()).collect());
+        let column = |field: &str| included(&include, field, &default);
+        Ok(QueryResult {
+            ids: rows.iter().map(|row| row.iter().map(|r| r.get(0)).collect()).collect(),
+            metadatas: column("metadatas")
+                .then(|| rows.iter()
+                    .map(|row| row.iter().map(|r| parse_metadata(r.get(2))).collect::<anyhow::Result<_>>())
+                    .collect::<anyhow::Result<_>>())
+                .transpose()?,
+            documents: column("documents")
+                .then(|| rows.iter().map(|row| row.iter().map(|r| r.get(1)).collect()).collect()),
+            embeddings: column("embeddings")
+                .then(|| rows.iter().map(|row| row.iter().map(|r| r.get(3)).collect()).collect()),
+            distances: column("distances")
+                .then(|| rows.iter().map(|row| row.iter().map(|r| r.get(4)).collect()).collect()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sql_filter() -> anyhow::Result<()> {
+        let mut values: Vec<Param> = vec![Box::new(vec![0f32])];
+        let mut filter = SqlFilter { params: &mut values };
+        let where_metadata = json!({"$and": [{"source": "a.pdf"}, {"year": {"$gte": 2023}}]});
+        let sql = filter.clause(&Some(where_metadata), &Some(json!({"$contains": "营收"})))?;
+
+        assert_eq!(sql, "(metadata -> $2::text = $3::text::jsonb AND \
+            ((jsonb_typeof(metadata -> $4::text) = 'number' AND (metadata -> $4::text)::text::float8 >= $5::float8))) \
+            AND strpos(document, $6) > 0");
+        assert_eq!(values.len(), 6);
+
+        let mut values: Vec<Param> = Vec::new();
+        assert!(SqlFilter { params: &mut values }.clause(&Some(json!({"year": {"$like": 1}})), &None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::Duration;
+use anyhow::Context;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use crate::chat::deepseek::ChatClient;
+use crate::chat::{FormatType, Role, Talk};
+use crate::Config;
+
+// 交给LLM打分时每个文段保留的字符数, 控制提示词长度
+const LLM_SNIPPET_CHARS: usize = 500;
+const TIMEOUT: u64 = 60;
+
+/// 检索后的重排方式, 对应配置中的 `reranker`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankMode {
+    #[default]
+    None,
+    /// 自建的交叉编码器服务(如 bge-reranker), 兼容 Jina/Cohere 的 `/rerank` 接口
+    CrossEncoder,
+    /// 由对话模型为每个候选打分
+    Llm,
+}
+
+pub enum Reranker {
+    CrossEncoder {
+        client: Client,
+        url: String,
+        model: String,
+    },
+    Llm,
+}
+
+#[derive(Serialize)]
+struct RerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Deserialize)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+#[derive(Deserialize)]
+struct LlmScores {
+    scores: Vec<f32>,
+}
+
+impl Reranker {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        match config.reranker {
+            RerankMode::None => None,
+            RerankMode::CrossEncoder => Some(Self::CrossEncoder {
+                client: Client::builder().timeout(Duration::from_secs(TIMEOUT)).build().unwrap_or_default(),
+                url: config.rerank_url.clone(),
+                model: config.rerank_model.clone(),
+            }),
+            RerankMode::Llm => Some(Self::Llm),
+        }
+    }
+
+    /// 返回每个候选与问题的相关性, 越大越相关, 顺序与 `documents` 一致
+    pub async fn score(&self, chat: &ChatClient, query: &str, documents: &[String]) -> anyhow::Result<Vec<f32>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+        match self {
+            Self::CrossEncoder { client, url, model } => {
+                let request = RerankRequest { model, query, documents };
+                let response = client.post(url).json(&request).send().await
+                    .with_context(|| format!("Failed to send request to {}", url))?;
+                if response.status() != 200 {
+                    return Err(anyhow::anyhow!("请求失败:\n\t错误码: {}\n\t内容: {}", response.status(), response.text().await?));
+                }
+                let results = response.json::<RerankResponse>().await
+                    .context("Failed to parse rerank response")?.results;
+
+                let mut scores = vec![f32::MIN; documents.len()];
+                for result in results {
+                    if let Some(score) = scores.get_mut(result.index) {
+                        *score = result.relevance_score;
+                    }
+                }
+                Ok(scores)
+            },
+            Self::Llm => llm_scores(chat, query, documents).await,
+        }
+    }
+}
+
+async fn llm_scores(chat: &ChatClient, query: &str, documents: &[String]) -> anyhow::Result<Vec<f32>> {
+    let passages = documents.iter().enumerate()
+        .map(|(i, doc)| format!("[{}] {}", i, doc.chars().take(LLM_SNIPPET_CHARS).collect::<String>()))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    let prompt = format!(
+        "请判断以下每个文段对回答问题的帮助程度, 按0到10打分, 10表示能直接回答问题, 0表示无关.\n\
+        以JSON格式输出, 形如 {{\"scores\": [7, 0, ...]}}, 分数的数量与顺序必须与文段一致.\n\n\
+        问题: {}\n\n文段:\n{}",
+        query, passages
+    );
+    let mut messages = vec![Talk::new(Role::User, prompt)];
+    chat.get_completion(&mut messages, FormatType::JsonObject).await?;
+
+    let content = messages.last().map(|talk| talk.content.as_str()).unwrap_or_default();
+    let scores = serde_json::from_str::<LlmScores>(content)
+        .with_context(|| format!("无法解析重排结果: {}", content))?.scores;
+    if scores.len() != documents.len() {
+        anyhow::bail!("重排结果数量为 {}, 与候选数 {} 不一致", scores.len(), documents.len());
+    }
+    Ok(scores.into_iter().map(|score| score / 10.0).collect())
+}